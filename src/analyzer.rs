@@ -1,7 +1,11 @@
 use crate::checks;
 use crate::checks::GlobalSeedUsage;
+use crate::checks::cpi_check::{CpiCatalog, CpiResolver};
 use crate::checks::mut_borrow_check::attr_contains_mut;
-use crate::utils::is_anchor_account_struct;
+use crate::index::{AccountIndex, is_account_attr};
+use crate::utils::{
+    AccountsStructMap, account_constraints, file_fns, find_constraint, is_anchor_account_struct,
+};
 use colored::*;
 use std::collections::HashMap;
 use std::collections::HashSet;
@@ -10,7 +14,7 @@ use std::path::Path;
 use syn::{Fields, File, Item, parse_file};
 use walkdir::WalkDir;
 
-pub fn analyze_path(path: &str) -> Result<(), String> {
+pub fn analyze_path(path: &str, catalog: &CpiCatalog) -> Result<(), String> {
     let path_obj = Path::new(path);
 
     if !path_obj.exists() {
@@ -21,6 +25,8 @@ pub fn analyze_path(path: &str) -> Result<(), String> {
         ));
     }
 
+    // parse everything first so checks can see types declared in other files
+    let mut parsed_files = Vec::new();
     for entry in WalkDir::new(path)
         .into_iter()
         .filter_map(Result::ok)
@@ -29,7 +35,11 @@ pub fn analyze_path(path: &str) -> Result<(), String> {
         let path_str = entry.path().to_str().unwrap();
         if let Ok(content) = fs::read_to_string(entry.path()) {
             match parse_file(&content) {
-                Ok(parsed) => analyze_file(&parsed, path_str),
+                Ok(parsed) => parsed_files.push((
+                    parsed,
+                    path_str.to_string(),
+                    release_overflow_checks(entry.path()),
+                )),
                 Err(e) => eprintln!(
                     "{} Failed to parse {}: {}",
                     "[ERROR]".red().bold(),
@@ -40,17 +50,64 @@ pub fn analyze_path(path: &str) -> Result<(), String> {
         }
     }
 
+    let index = AccountIndex::from_files(parsed_files.iter().map(|(file, _, _)| file));
+    for (parsed, path_str, overflow_checks) in &parsed_files {
+        analyze_file(parsed, path_str, catalog, &index, *overflow_checks);
+    }
+
     Ok(())
 }
 
-fn analyze_file(file: &File, filename: &str) {
+/// Whether the nearest `Cargo.toml` above `file` (up to the workspace root)
+/// sets `overflow-checks = true` under `[profile.release]`.
+fn release_overflow_checks(file: &Path) -> bool {
+    for dir in file.ancestors().skip(1) {
+        let Ok(manifest) = fs::read_to_string(dir.join("Cargo.toml")) else {
+            continue;
+        };
+
+        let mut section = "";
+        for line in manifest.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.starts_with('[') {
+                section = line.trim_matches(|c| c == '[' || c == ']').trim();
+            } else if section == "profile.release"
+                && let Some((key, value)) = line.split_once('=')
+                && key.trim() == "overflow-checks"
+            {
+                return value.trim() == "true";
+            }
+        }
+
+        // profiles only take effect in the workspace root
+        if manifest.lines().any(|l| l.trim() == "[workspace]") {
+            break;
+        }
+    }
+    false
+}
+
+fn analyze_file(
+    file: &File,
+    filename: &str,
+    catalog: &CpiCatalog,
+    index: &AccountIndex,
+    overflow_checks: bool,
+) {
+    let cpi = CpiResolver::new(file, catalog);
     let mut all_seeds = Vec::<GlobalSeedUsage>::new();
     let mut accounts_mut_fields: HashMap<String, HashSet<String>> = HashMap::new();
     let mut accounts_init_fields: HashMap<String, HashSet<String>> = HashMap::new();
+    let mut accounts_structs: AccountsStructMap = HashMap::new();
+    let fns = file_fns(file);
 
     // ==== PASS 1: Struct‐level checks & collect maps / seeds ====
     for item in &file.items {
         if let Item::Struct(s) = item {
+            if is_account_attr(&s.attrs) {
+                checks::collection_check::check_unbounded_fields(s, filename);
+            }
+            checks::zero_copy_check::check_zero_copy_struct(s, filename, index);
             if is_anchor_account_struct(&s.attrs) {
                 // Info
                 let line = s.ident.span().start().line;
@@ -65,8 +122,19 @@ fn analyze_file(file: &File, filename: &str) {
                 // existing per‐struct checks
                 checks::signer_check::check_missing_signer(s, filename);
                 checks::state_overwrite_check::check_duplicate_account_types(s, filename);
-                checks::init_if_needed_check::check_init_if_needed(s, filename);
+                checks::init_if_needed_check::check_init_if_needed(s, filename, &fns);
                 checks::seeds_reuse_check::collect_seeds(s, filename, &mut all_seeds);
+                checks::seeds_reuse_check::check_seed_constraints(s, filename);
+                checks::bump_check::check_bump_constraints(s, filename);
+                checks::close_check::check_close_constraints(s, filename);
+                checks::sysvar_check::check_sysvar_accounts(s, filename);
+                checks::init_frontrun_check::check_global_init(s, filename);
+                checks::realloc_check::check_realloc_constraints(s, filename);
+                checks::space_check::check_account_space(s, filename, index);
+                checks::zero_copy_check::check_account_loaders(s, filename, index);
+                checks::token_check::check_token_program_mismatch(s, filename);
+                checks::oracle_check::check_oracle_accounts(s, filename);
+                accounts_structs.insert(s.ident.to_string(), s);
 
                 // build the mut‐map
                 let mut set = HashSet::new();
@@ -85,20 +153,12 @@ fn analyze_file(file: &File, filename: &str) {
                 let mut inited = HashSet::new();
                 if let Fields::Named(fields) = &s.fields {
                     for f in &fields.named {
-                        for attr in &f.attrs {
-                            // look for either `init` or `init_if_needed`
-                            if attr
-                                .parse_nested_meta(|meta| {
-                                    let name = meta.path.get_ident().unwrap().to_string();
-                                    if name == "init" || name == "init_if_needed" {
-                                        return Err(syn::Error::new_spanned(meta.path.clone(), ""));
-                                    }
-                                    Ok(())
-                                })
-                                .is_err()
-                            {
-                                inited.insert(f.ident.as_ref().unwrap().to_string());
-                            }
+                        // look for either `init` or `init_if_needed`
+                        let constraints = account_constraints(f);
+                        if find_constraint(&constraints, "init").is_some()
+                            || find_constraint(&constraints, "init_if_needed").is_some()
+                        {
+                            inited.insert(f.ident.as_ref().unwrap().to_string());
                         }
                     }
                 }
@@ -134,10 +194,89 @@ fn analyze_file(file: &File, filename: &str) {
                                 fl
                             );
                             checks::cpi_check::detect_cpi_in_fn(func, filename);
+                            checks::cpi_check::check_cpi_context_signers(
+                                func,
+                                filename,
+                                &accounts_structs,
+                            );
+                            checks::reload_check::check_reload_after_cpi(
+                                func,
+                                filename,
+                                &accounts_structs,
+                                &cpi,
+                            );
+                            checks::access_control_check::check_access_control(
+                                func,
+                                filename,
+                                &accounts_structs,
+                            );
+                            checks::signer_seeds_check::check_signer_seeds(
+                                func,
+                                filename,
+                                &accounts_structs,
+                            );
+                            checks::signer_seeds_check::check_pda_sharing(
+                                func,
+                                filename,
+                                &accounts_structs,
+                            );
+                            checks::bump_check::check_bump_usage(func, filename, &accounts_structs);
+                            checks::close_check::check_manual_close(func, filename);
+                            checks::arithmetic_check::check_unchecked_arithmetic(
+                                func,
+                                filename,
+                                overflow_checks,
+                            );
+                            checks::precision_check::check_precision_loss(func, filename);
+                            checks::sysvar_check::check_sysvar_usage(
+                                func,
+                                filename,
+                                &accounts_structs,
+                            );
+                            checks::introspection_check::check_instruction_introspection(
+                                func,
+                                filename,
+                                &accounts_structs,
+                            );
+                            checks::collection_check::check_unbounded_pushes(
+                                func,
+                                filename,
+                                &accounts_structs,
+                                index,
+                            );
+                            checks::token_check::check_token_constraints(
+                                func,
+                                filename,
+                                &accounts_structs,
+                                &cpi,
+                            );
+                            checks::token_check::check_token_2022(
+                                func,
+                                filename,
+                                &accounts_structs,
+                                &cpi,
+                            );
+                            checks::oracle_check::check_oracle_usage(
+                                func,
+                                filename,
+                                &accounts_structs,
+                                index,
+                            );
+                            checks::type_cosplay_check::check_type_cosplay(
+                                func,
+                                filename,
+                                &accounts_structs,
+                            );
                             checks::remaining_accounts_check::check_remaining_accounts_usage(
                                 func, filename,
                             );
                             checks::realloc_check::check_realloc_usage(func, filename);
+                            checks::lamports_check::check_lamport_transfers(
+                                func,
+                                filename,
+                                &accounts_structs,
+                                &cpi,
+                            );
                             checks::mut_borrow_check::check_mut_borrow(
                                 func,
                                 filename,
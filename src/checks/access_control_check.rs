@@ -0,0 +1,279 @@
+use crate::utils::{
+    AccountsStructMap, account_constraints, account_ref, account_type, accounts_in, collect_locals,
+    context_struct_name, find_constraint, find_field, mentioned_ident, strip_ref,
+};
+use crate::visit::{ExprVisitor, walk_expr, walk_fn};
+use colored::*;
+use std::collections::{HashMap, HashSet};
+use syn::{
+    BinOp, Expr, Fields, ItemFn, ItemStruct, Macro, Member, Token, punctuated::Punctuated,
+    spanned::Spanned,
+};
+
+/// Name parts of fields that only an administrator should change.
+const PRIVILEGED_FIELDS: &[&str] = &[
+    "fee",
+    "fees",
+    "bps",
+    "admin",
+    "authority",
+    "owner",
+    "paused",
+    "pause",
+    "treasury",
+    "operator",
+    "guardian",
+    "manager",
+];
+
+/// Flag handlers that write privileged fields (fees, admin keys, pause flags,
+/// authorities) while their Accounts struct never ties a `Signer` to a stored
+/// authority.
+pub fn check_access_control(func: &ItemFn, file: &str, accounts_structs: &AccountsStructMap) {
+    let Some(item_struct) =
+        context_struct_name(func).and_then(|n| accounts_structs.get(&n).copied())
+    else {
+        return;
+    };
+    if func
+        .attrs
+        .iter()
+        .any(|a| a.path().is_ident("access_control"))
+    {
+        return;
+    }
+    let locals = collect_locals(func);
+
+    // first privileged write: (account, field path, line)
+    let mut privileged = None;
+    walk_fn(func, &mut |expr: &Expr| {
+        let left = match expr {
+            Expr::Assign(a) => &a.left,
+            Expr::Binary(b)
+                if matches!(
+                    b.op,
+                    BinOp::AddAssign(_)
+                        | BinOp::SubAssign(_)
+                        | BinOp::MulAssign(_)
+                        | BinOp::DivAssign(_)
+                ) =>
+            {
+                &b.left
+            }
+            _ => return,
+        };
+        if privileged.is_some() {
+            return;
+        }
+        let Some((account, path)) = written_field(left, &locals) else {
+            return;
+        };
+        let Some(field) = find_field(item_struct, &account) else {
+            return;
+        };
+        // initialization writes the first authority, see init_frontrun_check
+        let cs = account_constraints(field);
+        if find_constraint(&cs, "init").is_some()
+            || find_constraint(&cs, "init_if_needed").is_some()
+        {
+            return;
+        }
+        // counters and balances on config accounts are ordinary user writes
+        if path.last().is_some_and(|f| has_hint(f, PRIVILEGED_FIELDS)) {
+            privileged = Some((account, path, left.span().start().line));
+        }
+    });
+    let Some((account, path, line)) = privileged else {
+        return;
+    };
+
+    let signers = signer_fields(item_struct);
+    if signer_bound(item_struct, &signers, &account)
+        || handler_checks_signer(func, &signers, &account, locals)
+    {
+        return;
+    }
+
+    let target = std::iter::once(account.as_str())
+        .chain(path.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(".");
+    let why = if signers.is_empty() {
+        format!("struct `{}` has no `Signer` at all", item_struct.ident)
+    } else {
+        format!(
+            "no `Signer` in struct `{}` ({}) is tied to an authority stored in `{}`",
+            item_struct.ident,
+            signers
+                .iter()
+                .map(|s| format!("`{}`", s))
+                .collect::<Vec<_>>()
+                .join(", "),
+            account
+        )
+    };
+    println!(
+        "{} `{}` writes privileged state `{}`, but {}; anyone can call it. \
+Bind the signer with `has_one` on `{}`, `address = ...`, a `constraint` comparing its key with a field of `{}`, or an `#[access_control(..)]` function. ({}:{})\n",
+        "[ERROR]".red().bold(),
+        func.sig.ident,
+        target,
+        why,
+        account,
+        account,
+        file,
+        line
+    );
+}
+
+/// The account and the field path written by `ctx.accounts.config.fees.bps = ..`
+/// or `config.fee = ..` through a local bound to `&mut ctx.accounts.config`.
+fn written_field(expr: &Expr, locals: &HashMap<String, Expr>) -> Option<(String, Vec<String>)> {
+    let mut path = Vec::new();
+    let mut current = expr;
+    loop {
+        if let Some(account) = account_ref(current, locals) {
+            path.reverse();
+            return (!path.is_empty()).then_some((account, path));
+        }
+        match current {
+            Expr::Field(f) => {
+                let Member::Named(m) = &f.member else {
+                    return None;
+                };
+                path.push(m.to_string());
+                current = &f.base;
+            }
+            Expr::Index(i) => current = &i.expr,
+            Expr::Paren(p) => current = &p.expr,
+            _ => return None,
+        }
+    }
+}
+
+fn signer_fields(item_struct: &ItemStruct) -> Vec<String> {
+    let Fields::Named(fields) = &item_struct.fields else {
+        return Vec::new();
+    };
+    fields
+        .named
+        .iter()
+        .filter(|f| account_type(&f.ty).is_some_and(|(wrapper, _)| wrapper == "Signer"))
+        .map(|f| f.ident.as_ref().unwrap().to_string())
+        .collect()
+}
+
+/// Whether a signer is pinned by its own `address`, named by a `has_one` on
+/// `account`, or compared with a field of `account` in some `constraint`.
+fn signer_bound(item_struct: &ItemStruct, signers: &[String], account: &str) -> bool {
+    let Fields::Named(fields) = &item_struct.fields else {
+        return false;
+    };
+    let names: HashSet<String> = signers.iter().cloned().collect();
+    let no_locals = HashMap::new();
+    fields.named.iter().any(|field| {
+        let name = field.ident.as_ref().unwrap().to_string();
+        account_constraints(field).iter().any(|c| {
+            let Some(value) = &c.value else {
+                return false;
+            };
+            match c.key.as_str() {
+                "address" => names.contains(&name),
+                "has_one" => name == account && mentioned_ident(value, &names).is_some(),
+                "constraint" => {
+                    let mut found = false;
+                    walk_expr(value, &mut |e: &Expr| {
+                        if let Expr::Binary(b) = e
+                            && matches!(b.op, BinOp::Eq(_))
+                            && compares_signer(&b.left, &b.right, &names, account, &no_locals)
+                        {
+                            found = true;
+                        }
+                    });
+                    found
+                }
+                _ => false,
+            }
+        })
+    })
+}
+
+/// Whether the handler compares a signer's key with a field of `account`,
+/// with `==`/`!=` or `require_keys_eq!`/`require_keys_neq!`.
+fn handler_checks_signer(
+    func: &ItemFn,
+    signers: &[String],
+    account: &str,
+    locals: HashMap<String, Expr>,
+) -> bool {
+    struct SignerChecks<'a> {
+        signers: HashSet<String>,
+        account: &'a str,
+        locals: HashMap<String, Expr>,
+        found: bool,
+    }
+
+    impl ExprVisitor for SignerChecks<'_> {
+        fn visit_expr(&mut self, expr: &Expr) {
+            if let Expr::Binary(b) = expr
+                && matches!(b.op, BinOp::Eq(_) | BinOp::Ne(_))
+            {
+                self.found |=
+                    compares_signer(&b.left, &b.right, &self.signers, self.account, &self.locals);
+            }
+        }
+
+        fn visit_macro(&mut self, mac: &Macro) {
+            if !(mac.path.is_ident("require_keys_eq") || mac.path.is_ident("require_keys_neq")) {
+                return;
+            }
+            if let Ok(args) = mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+                && let [left, right, ..] = args.iter().collect::<Vec<_>>()[..]
+            {
+                self.found |=
+                    compares_signer(left, right, &self.signers, self.account, &self.locals);
+            }
+        }
+    }
+
+    let mut checks = SignerChecks {
+        signers: signers.iter().cloned().collect(),
+        account,
+        locals,
+        found: false,
+    };
+    walk_fn(func, &mut checks);
+    checks.found
+}
+
+/// Whether one side of a comparison is a signer's key and the other a field
+/// of `account`, e.g. `admin.key() == config.admin`.
+fn compares_signer(
+    left: &Expr,
+    right: &Expr,
+    signers: &HashSet<String>,
+    account: &str,
+    locals: &HashMap<String, Expr>,
+) -> bool {
+    let is_signer = |e: &Expr| {
+        !accounts_in(e, locals).is_disjoint(signers) || mentioned_ident(e, signers).is_some()
+    };
+    (is_signer(left) && field_of(right, account, locals))
+        || (is_signer(right) && field_of(left, account, locals))
+}
+
+/// Whether `expr` reads a field of `account`: `config.admin`,
+/// `ctx.accounts.config.fees.admin`, or through a local bound to the account.
+fn field_of(expr: &Expr, account: &str, locals: &HashMap<String, Expr>) -> bool {
+    let Expr::Field(f) = strip_ref(expr) else {
+        return false;
+    };
+    let base = strip_ref(&f.base);
+    account_ref(base, locals).as_deref() == Some(account)
+        || matches!(base, Expr::Path(p) if p.path.is_ident(account))
+        || field_of(base, account, locals)
+}
+
+fn has_hint(name: &str, hints: &[&str]) -> bool {
+    name.split('_').any(|part| hints.contains(&part))
+}
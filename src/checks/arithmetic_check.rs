@@ -0,0 +1,137 @@
+use crate::utils::{account_ref, collect_locals, instruction_args};
+use crate::visit::walk_fn;
+use colored::*;
+use proc_macro2::LineColumn;
+use std::collections::{HashMap, HashSet};
+use syn::{BinOp, Expr, ItemFn, Member, spanned::Spanned};
+
+/// Flag `+`, `-`, `*` (and their compound forms) on values that come from
+/// account state or instruction arguments. `overflow_checks` is whether the
+/// crate's `[profile.release]` sets `overflow-checks = true`.
+pub fn check_unchecked_arithmetic(func: &ItemFn, file: &str, overflow_checks: bool) {
+    let locals = collect_locals(func);
+    let args = instruction_args(func);
+    let fn_name = func.sig.ident.to_string();
+
+    // spans already reported, so `a + b + c` is reported once
+    let mut reported: Vec<(LineColumn, LineColumn)> = Vec::new();
+
+    walk_fn(func, &mut |expr: &Expr| {
+        let Expr::Binary(bin) = expr else {
+            return;
+        };
+        let Some(checked) = checked_method(&bin.op) else {
+            return;
+        };
+        let span = bin.span();
+        let (start, end) = (span.start(), span.end());
+        let pos = |p: LineColumn| (p.line, p.column);
+        if reported
+            .iter()
+            .any(|(s, e)| pos(*s) <= pos(start) && pos(end) <= pos(*e))
+        {
+            return;
+        }
+
+        let Some(source) = tainted_source(&bin.left, &locals, &args, 0)
+            .or_else(|| tainted_source(&bin.right, &locals, &args, 0))
+        else {
+            return;
+        };
+        reported.push((start, end));
+
+        let op = bin.op.span().start();
+        let op_str = op_symbol(&bin.op);
+        if overflow_checks {
+            println!(
+                "{} Unchecked `{}` on `{}` in `{}` aborts the transaction on overflow (overflow-checks is on). \
+Prefer `{}` with a descriptive error. ({}:{})\n",
+                "[WARNING]".yellow().bold(),
+                op_str,
+                source,
+                fn_name,
+                checked,
+                file,
+                op.line
+            );
+        } else {
+            println!(
+                "{} Unchecked `{}` on `{}` in `{}` wraps silently in release builds: `overflow-checks` is not enabled in `[profile.release]`. \
+Use `{}` or the `saturating_*` variant. ({}:{})\n",
+                "[ERROR]".red().bold(),
+                op_str,
+                source,
+                fn_name,
+                checked,
+                file,
+                op.line
+            );
+        }
+    });
+}
+
+fn checked_method(op: &BinOp) -> Option<&'static str> {
+    match op {
+        BinOp::Add(_) | BinOp::AddAssign(_) => Some("checked_add"),
+        BinOp::Sub(_) | BinOp::SubAssign(_) => Some("checked_sub"),
+        BinOp::Mul(_) | BinOp::MulAssign(_) => Some("checked_mul"),
+        _ => None,
+    }
+}
+
+fn op_symbol(op: &BinOp) -> &'static str {
+    match op {
+        BinOp::Add(_) => "+",
+        BinOp::AddAssign(_) => "+=",
+        BinOp::Sub(_) => "-",
+        BinOp::SubAssign(_) => "-=",
+        BinOp::Mul(_) => "*",
+        BinOp::MulAssign(_) => "*=",
+        _ => "?",
+    }
+}
+
+/// The account field (`vault.amount`) or instruction argument an operand is
+/// derived from, following local bindings.
+pub fn tainted_source(
+    expr: &Expr,
+    locals: &HashMap<String, Expr>,
+    args: &HashSet<String>,
+    depth: usize,
+) -> Option<String> {
+    if depth > 8 {
+        return None;
+    }
+    let taint = |e: &Expr| tainted_source(e, locals, args, depth + 1);
+    match expr {
+        Expr::Path(p) => {
+            let name = p.path.get_ident()?.to_string();
+            if args.contains(&name) {
+                Some(name)
+            } else {
+                locals.get(&name).and_then(taint)
+            }
+        }
+        Expr::Field(f) => match (&f.member, account_ref(&f.base, locals)) {
+            (Member::Named(member), Some(account)) => Some(format!("{}.{}", account, member)),
+            _ => taint(&f.base),
+        },
+        // lengths and keys are not amounts
+        Expr::MethodCall(mc)
+            if matches!(mc.method.to_string().as_str(), "len" | "key" | "count") =>
+        {
+            None
+        }
+        Expr::MethodCall(mc) => taint(&mc.receiver).or_else(|| mc.args.iter().find_map(taint)),
+        Expr::Call(c) => c.args.iter().find_map(taint),
+        Expr::Binary(b) => taint(&b.left).or_else(|| taint(&b.right)),
+        Expr::Unary(u) => taint(&u.expr),
+        Expr::Paren(p) => taint(&p.expr),
+        Expr::Group(g) => taint(&g.expr),
+        Expr::Cast(c) => taint(&c.expr),
+        Expr::Try(t) => taint(&t.expr),
+        Expr::Reference(r) => taint(&r.expr),
+        Expr::Index(i) => taint(&i.expr),
+        _ => None,
+    }
+}
@@ -0,0 +1,143 @@
+use crate::checks::signer_seeds_check::{
+    bump_from_args, pda_with_seeds, resolve_array, split_bump,
+};
+use crate::utils::{
+    AccountsStructMap, account_constraints, collect_locals, context_struct_name, find_constraint,
+    instruction_args, instruction_attr_args, mentioned_ident, reads_account_data,
+};
+use crate::visit::{walk_block, walk_fn};
+use colored::*;
+use std::collections::HashSet;
+use syn::{Expr, ExprCall, ExprPath, Fields, ItemFn, ItemStruct, spanned::Spanned};
+
+/// Flag `bump = <expr>` constraints whose bump comes from `#[instruction(...)]` input.
+pub fn check_bump_constraints(item_struct: &ItemStruct, file: &str) {
+    let ix_args = instruction_attr_args(item_struct);
+    if ix_args.is_empty() {
+        return;
+    }
+
+    if let Fields::Named(fields) = &item_struct.fields {
+        for field in &fields.named {
+            let constraints = account_constraints(field);
+            let Some(bump) = find_constraint(&constraints, "bump").and_then(|c| c.value.as_ref())
+            else {
+                continue;
+            };
+            if let Some(arg) = mentioned_ident(bump, &ix_args) {
+                let line = bump.span().start().line;
+                println!(
+                    "{} `bump` of `{}` in struct `{}` is taken from instruction argument `{}`, so callers can pick a non-canonical bump. \
+Use a bare `bump` or the bump stored at initialization. ({}:{})\n",
+                    "[ERROR]".red().bold(),
+                    field.ident.as_ref().unwrap(),
+                    item_struct.ident,
+                    arg,
+                    file,
+                    line
+                );
+            }
+        }
+    }
+}
+
+/// Flag `create_program_address` with a user-controlled bump, and
+/// `find_program_address` on hot paths: inside loops, or re-deriving a PDA
+/// whose canonical bump `ctx.bumps` already holds.
+pub fn check_bump_usage(func: &ItemFn, file: &str, accounts_structs: &AccountsStructMap) {
+    let fn_name = func.sig.ident.to_string();
+    let locals = collect_locals(func);
+    let args = instruction_args(func);
+    let item_struct = context_struct_name(func).and_then(|n| accounts_structs.get(&n).copied());
+
+    // lines of calls that run once per loop iteration
+    let mut in_loop = HashSet::new();
+    walk_fn(func, &mut |expr: &Expr| {
+        let body = match expr {
+            Expr::ForLoop(l) => &l.body,
+            Expr::While(l) => &l.body,
+            Expr::Loop(l) => &l.body,
+            _ => return,
+        };
+        walk_block(body, &mut |inner: &Expr| {
+            if let Expr::Call(call) = inner
+                && let Expr::Path(p) = &*call.func
+                && p.path.segments.last().unwrap().ident == "find_program_address"
+            {
+                in_loop.insert(inner.span().start().line);
+            }
+        });
+    });
+
+    walk_fn(func, &mut |expr: &Expr| {
+        let Expr::Call(ExprCall {
+            func: callee,
+            args: call_args,
+            ..
+        }) = expr
+        else {
+            return;
+        };
+        let Expr::Path(ExprPath { path, .. }) = &**callee else {
+            return;
+        };
+        let name = path.segments.last().unwrap().ident.to_string();
+        let line = expr.span().start().line;
+
+        if name == "find_program_address" {
+            let pda = item_struct.zip(call_args.first()).and_then(|(s, seeds)| {
+                resolve_array(seeds, &locals, 0)
+                    .and_then(|seeds| pda_with_seeds(s, &seeds, &locals))
+            });
+            if let Some(pda) = pda {
+                println!(
+                    "{} `find_program_address` in `{}` re-derives `{}`, whose canonical bump Anchor already found; it can cost up to 255 hash attempts. \
+Use `ctx.bumps.{}` instead. ({}:{})\n",
+                    "[WARNING]".yellow().bold(),
+                    fn_name,
+                    pda,
+                    pda,
+                    file,
+                    line
+                );
+            } else if in_loop.contains(&line) {
+                println!(
+                    "{} `find_program_address` runs inside a loop in `{}`; each call can cost up to 255 hash attempts. \
+Store the bump in the account and use `create_program_address` with it. ({}:{})\n",
+                    "[WARNING]".yellow().bold(),
+                    fn_name,
+                    file,
+                    line
+                );
+            }
+        } else if name == "create_program_address" {
+            let Some(seeds) = call_args.first().and_then(|s| resolve_array(s, &locals, 0)) else {
+                return;
+            };
+            let Some(bump) = split_bump(&seeds).1 else {
+                return;
+            };
+
+            if let Some(arg) = bump_from_args(bump, &locals, &args) {
+                println!(
+                    "{} `create_program_address` in `{}` uses bump `{}` from instruction data, which need not be canonical. \
+Derive it with `ctx.bumps` or a stored canonical bump. ({}:{})\n",
+                    "[ERROR]".red().bold(),
+                    fn_name,
+                    arg,
+                    file,
+                    line
+                );
+            } else if reads_account_data(bump, &locals) {
+                println!(
+                    "{} `create_program_address` in `{}` takes its bump from raw account data that is not validated. \
+Derive it with `ctx.bumps` or a stored canonical bump. ({}:{})\n",
+                    "[WARNING]".yellow().bold(),
+                    fn_name,
+                    file,
+                    line
+                );
+            }
+        }
+    });
+}
@@ -0,0 +1,142 @@
+use crate::utils::{
+    LamportChange, LamportWrite, account_constraints, account_type, accounts_in, collect_locals,
+    find_constraint, int_lit, lamport_write,
+};
+use crate::visit::{walk_expr, walk_fn};
+use colored::*;
+use std::collections::{HashMap, HashSet};
+use syn::{Expr, Fields, ItemFn, ItemStruct, spanned::Spanned};
+
+/// Warn on `close = target` where nothing ties `target` to the caller.
+pub fn check_close_constraints(item_struct: &ItemStruct, file: &str) {
+    let Fields::Named(fields) = &item_struct.fields else {
+        return;
+    };
+
+    let mut trusted = HashSet::new();
+    let mut closes = Vec::new();
+    for field in &fields.named {
+        let name = field.ident.as_ref().unwrap().to_string();
+        let constraints = account_constraints(field);
+
+        if account_type(&field.ty).is_some_and(|(wrapper, _)| wrapper == "Signer")
+            || find_constraint(&constraints, "address").is_some()
+        {
+            trusted.insert(name.clone());
+        }
+        for c in constraints.iter().filter(|c| c.key == "has_one") {
+            if let Some(Expr::Path(p)) = &c.value
+                && let Some(ident) = p.path.get_ident()
+            {
+                trusted.insert(ident.to_string());
+            }
+        }
+        if let Some(Expr::Path(p)) =
+            find_constraint(&constraints, "close").and_then(|c| c.value.as_ref())
+            && let Some(target) = p.path.get_ident()
+        {
+            closes.push((name, target.to_string(), p.span().start().line));
+        }
+    }
+
+    for (account, target, line) in closes {
+        if trusted.contains(&target) {
+            continue;
+        }
+        println!(
+            "{} `{}` in struct `{}` is closed to `{}`, which is neither a `Signer` nor bound by `has_one`. \
+Anyone could redirect the rent to an arbitrary account. ({}:{})\n",
+            "[WARNING]".yellow().bold(),
+            account,
+            item_struct.ident,
+            target,
+            file,
+            line
+        );
+    }
+}
+
+/// Flag accounts closed by hand (lamports drained to zero) without zeroing
+/// their data or handing them back to the System Program, which allows the
+/// account to be revived within the same transaction.
+pub fn check_manual_close(func: &ItemFn, file: &str) {
+    let locals = collect_locals(func);
+    let mut drained: HashMap<String, usize> = HashMap::new();
+    let mut zeroed = HashSet::new();
+    let mut reassigned = HashSet::new();
+
+    walk_fn(func, &mut |expr: &Expr| {
+        // `**acc.try_borrow_mut_lamports()? = 0` or `-= acc.lamports()`
+        if let Some(LamportWrite {
+            account,
+            change: LamportChange::Drain,
+        }) = lamport_write(expr, &locals)
+        {
+            drained.entry(account).or_insert(expr.span().start().line);
+            return;
+        }
+        match expr {
+            Expr::MethodCall(mc) => {
+                let method = mc.method.to_string();
+                let first_arg_zero = mc.args.first().and_then(int_lit) == Some(0);
+                let closed_marker = mc.args.iter().any(mentions_closed_discriminator);
+                if (matches!(method.as_str(), "fill" | "realloc" | "resize") && first_arg_zero)
+                    || (method == "copy_from_slice" && closed_marker)
+                {
+                    zeroed.extend(accounts_in(&mc.receiver, &locals));
+                } else if method == "assign" {
+                    reassigned.extend(accounts_in(&mc.receiver, &locals));
+                }
+            }
+            Expr::Call(call) => {
+                if let Expr::Path(p) = &*call.func {
+                    let name = p.path.segments.last().unwrap().ident.to_string();
+                    let touched = call.args.iter().flat_map(|a| accounts_in(a, &locals));
+                    match name.as_str() {
+                        "sol_memset" => zeroed.extend(touched),
+                        "assign" => reassigned.extend(touched),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    });
+
+    let fn_name = func.sig.ident.to_string();
+    let mut drained: Vec<_> = drained.into_iter().collect();
+    drained.sort_by_key(|(_, line)| *line);
+    for (account, line) in drained {
+        let data_zeroed = zeroed.contains(&account);
+        let owner_reset = reassigned.contains(&account);
+        let (level, missing) = match (data_zeroed, owner_reset) {
+            (true, true) => continue,
+            (false, false) => (
+                "[ERROR]".red().bold(),
+                "zeroing its data or reassigning it to the System Program",
+            ),
+            (true, false) => (
+                "[WARNING]".yellow().bold(),
+                "reassigning it to the System Program",
+            ),
+            (false, true) => ("[WARNING]".yellow().bold(), "zeroing its data"),
+        };
+        println!(
+            "{} `{}` is closed manually in `{}` by draining its lamports without {}; it can be revived within the same transaction. \
+Use the `close = <target>` constraint or `.close()`. ({}:{})\n",
+            level, account, fn_name, missing, file, line
+        );
+    }
+}
+
+fn mentions_closed_discriminator(expr: &Expr) -> bool {
+    let mut found = false;
+    walk_expr(expr, &mut |e: &Expr| {
+        if let Expr::Path(p) = e
+            && p.path.segments.last().unwrap().ident == "CLOSED_ACCOUNT_DISCRIMINATOR"
+        {
+            found = true;
+        }
+    });
+    found
+}
@@ -0,0 +1,156 @@
+use crate::index::AccountIndex;
+use crate::utils::{
+    AccountsStructMap, account_ref, account_type, collect_locals, context_struct_name, find_field,
+};
+use crate::visit::walk_fn;
+use colored::*;
+use std::collections::{BTreeMap, HashSet};
+use syn::{Expr, Fields, GenericArgument, ItemFn, ItemStruct, Member, PathArguments, Type};
+
+/// Growable collections and whether `#[max_len]` can bound them.
+const COLLECTIONS: &[(&str, bool)] = &[
+    ("Vec", true),
+    ("String", true),
+    ("VecDeque", false),
+    ("HashMap", false),
+    ("BTreeMap", false),
+    ("HashSet", false),
+    ("BTreeSet", false),
+];
+
+/// Methods that grow a collection.
+const GROW_METHODS: &[&str] = &[
+    "push",
+    "push_str",
+    "push_back",
+    "insert",
+    "extend",
+    "append",
+];
+
+/// Flag collection fields of an `#[account]` struct that have no `#[max_len]`
+/// bound (or cannot have one).
+pub fn check_unbounded_fields(item_struct: &ItemStruct, file: &str) {
+    let Fields::Named(fields) = &item_struct.fields else {
+        return;
+    };
+    for field in &fields.named {
+        let Some((collection, boundable)) = collection_type(&field.ty) else {
+            continue;
+        };
+        let bounded = field.attrs.iter().any(|a| a.path().is_ident("max_len"));
+        if bounded && boundable {
+            continue;
+        }
+        let name = field.ident.as_ref().unwrap();
+        let advice = if boundable {
+            "Add `#[max_len(..)]` and size the account from it".to_string()
+        } else {
+            format!(
+                "`{}` has no fixed bound; use a `Vec` with `#[max_len(..)]` or a fixed-size array",
+                collection
+            )
+        };
+        println!(
+            "{} `{}` in account `{}` is an unbounded `{}`; the account can grow past its allocated space (and the 10 KiB `realloc` step) and stop deserializing. {}. ({}:{})\n",
+            "[WARNING]".yellow().bold(),
+            name,
+            item_struct.ident,
+            collection,
+            advice,
+            file,
+            name.span().start().line
+        );
+    }
+}
+
+/// Flag handlers that grow a collection stored in an account without ever
+/// checking its length.
+pub fn check_unbounded_pushes(
+    func: &ItemFn,
+    file: &str,
+    accounts_structs: &AccountsStructMap,
+    index: &AccountIndex,
+) {
+    let Some(item_struct) =
+        context_struct_name(func).and_then(|n| accounts_structs.get(&n).copied())
+    else {
+        return;
+    };
+    let locals = collect_locals(func);
+    let fn_name = func.sig.ident.to_string();
+
+    // `vault.items` → first line it grows on
+    let mut grown: BTreeMap<(String, String), usize> = BTreeMap::new();
+    let mut measured: HashSet<(String, String)> = HashSet::new();
+    walk_fn(func, &mut |expr: &Expr| {
+        let Expr::MethodCall(mc) = expr else {
+            return;
+        };
+        let Expr::Field(f) = &*mc.receiver else {
+            return;
+        };
+        let (Member::Named(member), Some(account)) = (&f.member, account_ref(&f.base, &locals))
+        else {
+            return;
+        };
+        let key = (account, member.to_string());
+        let method = mc.method.to_string();
+        if method == "len" || method == "is_empty" {
+            measured.insert(key);
+        } else if GROW_METHODS.contains(&method.as_str()) {
+            grown.entry(key).or_insert(mc.method.span().start().line);
+        }
+    });
+
+    for ((account, member), line) in grown {
+        if measured.contains(&(account.clone(), member.clone())) {
+            continue;
+        }
+        let data_type = find_field(item_struct, &account)
+            .and_then(|f| account_type(&f.ty))
+            .and_then(|(_, inner)| inner)
+            .and_then(|inner| index.account(&inner));
+        let is_collection = data_type.is_some_and(|s| {
+            matches!(&s.fields, Fields::Named(fields) if fields.named.iter().any(|f| {
+                f.ident.as_ref().is_some_and(|i| *i == member) && collection_type(&f.ty).is_some()
+            }))
+        });
+        if !is_collection {
+            continue;
+        }
+        println!(
+            "{} `{}.{}` grows in `{}` without a length check; once it fills the allocated space every write fails and the account is bricked. \
+Check `.len()` against the bound first. ({}:{})\n",
+            "[WARNING]".yellow().bold(),
+            account,
+            member,
+            fn_name,
+            file,
+            line
+        );
+    }
+}
+
+/// The growable collection `ty` is (looking through `Option`/`Box`), and
+/// whether `#[max_len]` applies to it.
+fn collection_type(ty: &Type) -> Option<(String, bool)> {
+    let Type::Path(tp) = ty else {
+        return None;
+    };
+    let seg = tp.path.segments.last()?;
+    let name = seg.ident.to_string();
+    if name == "Option" || name == "Box" {
+        let PathArguments::AngleBracketed(ab) = &seg.arguments else {
+            return None;
+        };
+        return ab.args.iter().find_map(|a| match a {
+            GenericArgument::Type(t) => collection_type(t),
+            _ => None,
+        });
+    }
+    COLLECTIONS
+        .iter()
+        .find(|(c, _)| *c == name)
+        .map(|(c, boundable)| (c.to_string(), *boundable))
+}
@@ -1,7 +1,14 @@
+use crate::utils::{
+    AccountsStructMap, Imports, account_constraints, account_info_ref, account_type,
+    collect_locals, context_struct_name, find_constraint, find_field, strip_ref,
+};
+use crate::visit::walk_fn;
 use colored::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use syn::{
     Expr, ExprBlock, ExprCall, ExprForLoop, ExprIf, ExprLoop, ExprMatch, ExprMethodCall, ExprPath,
-    ExprReference, ExprWhile, ItemFn, Stmt, spanned::Spanned,
+    ExprReference, ExprStruct, ExprWhile, File, Item, ItemFn, ItemStruct, Member, Stmt,
+    spanned::Spanned,
 };
 
 fn detect_invoke_signed_bump(call: &ExprCall, file: &str, fn_name: &str) {
@@ -80,23 +87,6 @@ fn detect_cpi_expr(expr: &Expr, filename: &str, fn_name: &str) {
     match expr {
         // function call: anchor_spl::token::transfer(...)
         Expr::Call(call) => {
-            if let Expr::Path(ExprPath { path, .. }) = call.func.as_ref() {
-                if let Some(seg) = path.segments.last() {
-                    let name = seg.ident.to_string();
-                    if is_known_cpi(&name) {
-                        let line = seg.ident.span().start().line;
-                        println!(
-                            "{} CPI `{}` in `{}`. Consider `.reload()?` on affected accounts. ({}:{})\n",
-                            "[WARNING]".yellow().bold(),
-                            name,
-                            fn_name,
-                            filename,
-                            line,
-                        );
-                    }
-                }
-            }
-
             detect_invoke_signed_bump(call, filename, fn_name);
 
             // dive into arguments
@@ -118,24 +108,7 @@ fn detect_cpi_expr(expr: &Expr, filename: &str, fn_name: &str) {
         }
 
         // method call: some_struct.invoke(...)
-        Expr::MethodCall(ExprMethodCall {
-            method,
-            receiver,
-            args,
-            ..
-        }) => {
-            let name = method.to_string();
-            if is_known_cpi(&name) {
-                let line = method.span().start().line;
-                println!(
-                    "{} CPI `{}` in `{}`. Consider `.reload()?` on affected accounts. ({}:{})\n",
-                    "[WARNING]".yellow().bold(),
-                    name,
-                    fn_name,
-                    filename,
-                    line,
-                );
-            }
+        Expr::MethodCall(ExprMethodCall { receiver, args, .. }) => {
             // recurse
             detect_cpi_expr(receiver, filename, fn_name);
             for arg in args {
@@ -206,15 +179,404 @@ fn detect_cpi_expr(expr: &Expr, filename: &str, fn_name: &str) {
     }
 }
 
-/// List of functions that perform CPIs in Anchor/SPL contexts
-fn is_known_cpi(name: &str) -> bool {
-    matches!(
-        name,
-        // Anchor direct CPIs
-        "invoke" | "invoke_signed"
-        // SPL token CPIs
-        | "transfer"
-        | "mint_to"
-        | "burn"
-    )
+/// CPI entry points, keyed by the path of the module that exposes them
+/// (`anchor_spl::token`, `anchor_lang::system_program`, ...). Programs we
+/// don't ship a list for can be added with `--cpi <module>::<function>`.
+pub struct CpiCatalog {
+    programs: HashMap<String, HashSet<String>>,
+}
+
+const TOKEN_CPIS: &[&str] = &[
+    "approve",
+    "approve_checked",
+    "burn",
+    "burn_checked",
+    "close_account",
+    "freeze_account",
+    "initialize_account",
+    "initialize_account3",
+    "initialize_mint",
+    "initialize_mint2",
+    "mint_to",
+    "mint_to_checked",
+    "revoke",
+    "set_authority",
+    "sync_native",
+    "thaw_account",
+    "transfer",
+    "transfer_checked",
+];
+
+const BUILTIN_CPIS: &[(&str, &[&str])] = &[
+    ("anchor_spl::token", TOKEN_CPIS),
+    ("anchor_spl::token_2022", TOKEN_CPIS),
+    ("anchor_spl::token_interface", TOKEN_CPIS),
+    (
+        "anchor_lang::system_program",
+        &[
+            "allocate",
+            "allocate_with_seed",
+            "assign",
+            "assign_with_seed",
+            "create_account",
+            "create_account_with_seed",
+            "transfer",
+            "transfer_with_seed",
+        ],
+    ),
+    (
+        "anchor_spl::associated_token",
+        &["create", "create_idempotent"],
+    ),
+    (
+        // also re-exported as `anchor_lang::solana_program::program`
+        "solana_program::program",
+        &[
+            "invoke",
+            "invoke_signed",
+            "invoke_signed_unchecked",
+            "invoke_unchecked",
+        ],
+    ),
+];
+
+impl Default for CpiCatalog {
+    fn default() -> Self {
+        let programs = BUILTIN_CPIS
+            .iter()
+            .map(|(program, fns)| {
+                let fns = fns.iter().map(|f| f.to_string()).collect();
+                (program.to_string(), fns)
+            })
+            .collect();
+        CpiCatalog { programs }
+    }
+}
+
+impl CpiCatalog {
+    /// Register an entry of the form `module::function`, where `module` may be
+    /// a full path such as `my_program::cpi`.
+    pub fn add(&mut self, entry: &str) -> Result<(), String> {
+        match entry.rsplit_once("::") {
+            Some((program, function))
+                if program.split("::").all(|s| !s.is_empty()) && !function.is_empty() =>
+            {
+                self.programs
+                    .entry(program.to_string())
+                    .or_default()
+                    .insert(function.to_string());
+                Ok(())
+            }
+            _ => Err(format!(
+                "{} Invalid CPI entry '{}', expected `<module>::<function>`.",
+                "[ERROR]".red().bold(),
+                entry
+            )),
+        }
+    }
+
+    /// Whether `function` in the module at `module_path` is a CPI. The
+    /// resolved path must end with the registered one, so `anchor_spl::token`
+    /// matches but a `token` module of the program itself does not.
+    fn contains(&self, module_path: &[String], function: &str) -> bool {
+        self.programs.iter().any(|(program, fns)| {
+            let program: Vec<&str> = program.split("::").collect();
+            fns.contains(function)
+                && module_path.len() >= program.len()
+                && module_path[module_path.len() - program.len()..]
+                    .iter()
+                    .zip(&program)
+                    .all(|(a, b)| a == b)
+        })
+    }
+
+    /// Whether a module whose last segment is `module` exposes `function`.
+    fn exposes(&self, module: &str, function: &str) -> bool {
+        self.programs.iter().any(|(program, fns)| {
+            program.rsplit("::").next() == Some(module) && fns.contains(function)
+        })
+    }
+}
+
+/// Recognizes CPI calls in one file, using its `use` imports to resolve paths.
+pub struct CpiResolver<'a> {
+    imports: Imports,
+    catalog: &'a CpiCatalog,
+    /// modules declared in the file, whose functions are never CPIs by path
+    local_mods: HashSet<String>,
+}
+
+impl<'a> CpiResolver<'a> {
+    pub fn new(file: &File, catalog: &'a CpiCatalog) -> Self {
+        let mut local_mods = HashSet::new();
+        collect_mods(&file.items, &mut local_mods);
+        CpiResolver {
+            imports: Imports::from_file(file),
+            catalog,
+            local_mods,
+        }
+    }
+
+    /// Whether `path` points into this crate rather than a dependency.
+    fn is_local(&self, path: &[String]) -> bool {
+        path.first().is_some_and(|first| {
+            matches!(first.as_str(), "crate" | "self" | "super") || self.local_mods.contains(first)
+        })
+    }
+
+    /// If `call` performs a CPI, return a short name for it such as
+    /// `token::transfer`. `locals` maps let-bound names to their initializer
+    /// so a `CpiContext` built earlier in the handler is still recognized.
+    pub fn cpi_name(&self, call: &ExprCall, locals: &HashMap<String, Expr>) -> Option<String> {
+        let Expr::Path(ExprPath { path, .. }) = &*call.func else {
+            return None;
+        };
+        let full = self.imports.resolve(path);
+        let function = full.last()?;
+        let module_path = &full[..full.len() - 1];
+
+        let module = if self.is_local(&full) {
+            None
+        } else if let Some(i) = module_path.iter().rposition(|s| s == "cpi")
+            && i > 0
+        {
+            // Anchor-generated `other_program::cpi::foo`
+            Some(module_path[i - 1].clone())
+        } else if !module_path.is_empty() {
+            self.catalog
+                .contains(module_path, function)
+                .then(|| module_path[module_path.len() - 1].clone())
+        } else {
+            // a bare name brought in by a glob import
+            self.imports
+                .globs()
+                .iter()
+                .filter(|prefix| !self.is_local(prefix))
+                .find(|prefix| self.catalog.contains(prefix, function))
+                .and_then(|prefix| prefix.last())
+                .cloned()
+        };
+
+        match module {
+            Some(module) => Some(format!("{}::{}", module, function)),
+            // `invoke`/`invoke_signed` are unambiguous even without an import,
+            // and anything taking a `CpiContext` is a CPI wrapper
+            None if full.len() == 1 && self.catalog.exposes("program", function) => {
+                Some(function.clone())
+            }
+            None if call.args.iter().any(|a| builds_cpi_context(a, locals)) => {
+                Some(full.join("::"))
+            }
+            None => None,
+        }
+    }
+}
+
+fn collect_mods(items: &[Item], out: &mut HashSet<String>) {
+    for item in items {
+        if let Item::Mod(m) = item {
+            out.insert(m.ident.to_string());
+            if let Some((_, inner)) = &m.content {
+                collect_mods(inner, out);
+            }
+        }
+    }
+}
+
+/// Whether `expr` is a `CpiContext::new(..)` / `new_with_signer(..)`, possibly
+/// through a local binding or a `.with_signer(..)` style builder chain.
+pub fn builds_cpi_context(expr: &Expr, locals: &HashMap<String, Expr>) -> bool {
+    match expr {
+        Expr::Call(ExprCall { func, .. }) => match &**func {
+            Expr::Path(ExprPath { path, .. }) => {
+                let mut segs = path.segments.iter().rev();
+                let ctor = segs.next().map(|s| s.ident.to_string()).unwrap_or_default();
+                segs.next().is_some_and(|s| s.ident == "CpiContext") && ctor.starts_with("new")
+            }
+            _ => false,
+        },
+        Expr::MethodCall(ExprMethodCall { receiver, .. }) => builds_cpi_context(receiver, locals),
+        Expr::Reference(ExprReference { expr, .. }) => builds_cpi_context(expr, locals),
+        Expr::Paren(p) => builds_cpi_context(&p.expr, locals),
+        Expr::Path(ExprPath { path, .. }) => path
+            .get_ident()
+            .and_then(|ident| locals.get(&ident.to_string()))
+            .is_some_and(|init| builds_cpi_context(init, locals)),
+        _ => false,
+    }
+}
+
+/// The pieces of a `CpiContext::new(program, accounts)` expression.
+pub struct CpiContextParts {
+    pub program: Expr,
+    /// the `Transfer { from, to, authority }` literal, if written inline or bound to a local
+    pub accounts: Option<ExprStruct>,
+    /// `.with_remaining_accounts(..)` appears in the builder chain
+    pub remaining_accounts: bool,
+    /// built with `new_with_signer` or `.with_signer(..)`
+    pub signed: bool,
+    /// line of the `CpiContext::new*` call
+    pub line: usize,
+}
+
+/// Decompose the `CpiContext` that `expr` builds, following locals and
+/// builder chains such as `.with_signer(..)` and `.with_remaining_accounts(..)`.
+pub fn cpi_context_parts(expr: &Expr, locals: &HashMap<String, Expr>) -> Option<CpiContextParts> {
+    fn resolve(
+        expr: &Expr,
+        locals: &HashMap<String, Expr>,
+        depth: usize,
+    ) -> Option<CpiContextParts> {
+        if depth > 8 {
+            return None;
+        }
+        match strip_ref(expr) {
+            Expr::Call(ExprCall { func, args, .. }) => {
+                let Expr::Path(ExprPath { path, .. }) = &**func else {
+                    return None;
+                };
+                let mut segs = path.segments.iter().rev();
+                let ctor = segs.next()?.ident.to_string();
+                let is_ctx =
+                    segs.next().is_some_and(|s| s.ident == "CpiContext") && ctor.starts_with("new");
+                if !is_ctx {
+                    return None;
+                }
+                let accounts = args.iter().nth(1).and_then(|a| struct_lit(a, locals, 0));
+                Some(CpiContextParts {
+                    program: args.first()?.clone(),
+                    accounts,
+                    remaining_accounts: false,
+                    signed: ctor == "new_with_signer",
+                    line: expr.span().start().line,
+                })
+            }
+            Expr::MethodCall(mc) => {
+                let mut parts = resolve(&mc.receiver, locals, depth + 1)?;
+                parts.remaining_accounts |= mc.method == "with_remaining_accounts";
+                parts.signed |= mc.method == "with_signer";
+                Some(parts)
+            }
+            Expr::Path(ExprPath { path, .. }) => {
+                let init = locals.get(&path.get_ident()?.to_string())?;
+                resolve(init, locals, depth + 1)
+            }
+            _ => None,
+        }
+    }
+
+    fn struct_lit(expr: &Expr, locals: &HashMap<String, Expr>, depth: usize) -> Option<ExprStruct> {
+        match strip_ref(expr) {
+            Expr::Struct(s) => Some(s.clone()),
+            Expr::Path(ExprPath { path, .. }) if depth < 8 => {
+                let init = locals.get(&path.get_ident()?.to_string())?;
+                struct_lit(init, locals, depth + 1)
+            }
+            _ => None,
+        }
+    }
+
+    resolve(expr, locals, 0)
+}
+
+/// The expression passed for `name` in a CPI accounts literal.
+pub fn cpi_account_field<'a>(accounts: &'a ExprStruct, name: &str) -> Option<&'a Expr> {
+    accounts.fields.iter().find_map(|fv| match &fv.member {
+        Member::Named(ident) if ident == name => Some(&fv.expr),
+        _ => None,
+    })
+}
+
+/// Account roles of a CPI to `program` that must sign: `from` for the System
+/// Program, the authority for token programs, nothing for others.
+pub fn signing_roles(
+    program: &Expr,
+    item_struct: &ItemStruct,
+    locals: &HashMap<String, Expr>,
+) -> &'static [&'static str] {
+    let Some(name) = account_info_ref(program, locals) else {
+        return &[];
+    };
+    let Some(field) = find_field(item_struct, &name) else {
+        return &[];
+    };
+    match account_type(&field.ty) {
+        Some((_, Some(inner))) if inner == "System" => &["from"],
+        Some((_, Some(inner))) if inner == "Token" || inner == "TokenInterface" => {
+            &["authority", "current_authority"]
+        }
+        _ if name.contains("system") => &["from"],
+        _ if name.contains("token") => &["authority", "current_authority"],
+        _ => &[],
+    }
+}
+
+/// Check that CPIs whose authority is a PDA are signed, and that signer seeds
+/// are not attached to CPIs whose authority is a user `Signer`.
+pub fn check_cpi_context_signers(func: &ItemFn, file: &str, accounts_structs: &AccountsStructMap) {
+    let Some(item_struct) =
+        context_struct_name(func).and_then(|n| accounts_structs.get(&n).copied())
+    else {
+        return;
+    };
+    let locals = collect_locals(func);
+    let fn_name = func.sig.ident.to_string();
+
+    // constructor line → context, merged with `.with_signer(..)` applied later
+    let mut contexts: BTreeMap<usize, CpiContextParts> = BTreeMap::new();
+    walk_fn(func, &mut |expr: &Expr| {
+        if matches!(expr, Expr::Call(_) | Expr::MethodCall(_))
+            && let Some(parts) = cpi_context_parts(expr, &locals)
+        {
+            match contexts.get_mut(&parts.line) {
+                Some(known) => known.signed |= parts.signed,
+                None => {
+                    contexts.insert(parts.line, parts);
+                }
+            }
+        }
+    });
+
+    for parts in contexts.values() {
+        let Some(accounts) = &parts.accounts else {
+            continue;
+        };
+        let roles = signing_roles(&parts.program, item_struct, &locals);
+
+        for role in roles {
+            let Some(authority) =
+                cpi_account_field(accounts, role).and_then(|e| account_info_ref(e, &locals))
+            else {
+                continue;
+            };
+            let Some(field) = find_field(item_struct, &authority) else {
+                continue;
+            };
+            let is_pda = find_constraint(&account_constraints(field), "seeds").is_some();
+            let is_signer = account_type(&field.ty).is_some_and(|(wrapper, _)| wrapper == "Signer");
+            if is_pda && !parts.signed {
+                println!(
+                    "{} CPI in `{}` uses PDA `{}` as `{}` but is built with `CpiContext::new`, so the PDA cannot sign. \
+Use `CpiContext::new_with_signer` with its seeds and bump. ({}:{})\n",
+                    "[ERROR]".red().bold(),
+                    fn_name,
+                    authority,
+                    role,
+                    file,
+                    parts.line
+                );
+            } else if is_signer && parts.signed {
+                println!(
+                    "{} CPI in `{}` attaches signer seeds but its `{}` is the user `Signer` `{}`; the seeds sign for nothing. \
+Check whether the PDA was meant to be the authority. ({}:{})\n",
+                    "[WARNING]".yellow().bold(),
+                    fn_name,
+                    role,
+                    authority,
+                    file,
+                    parts.line
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,103 @@
+use crate::checks::signer_seeds_check::is_constant_seed;
+use crate::utils::{account_constraints, account_type, constraint_seeds, find_constraint};
+use crate::visit::walk_expr;
+use colored::*;
+use quote::ToTokens;
+use std::collections::HashMap;
+use syn::{BinOp, Expr, Fields, ItemStruct, spanned::Spanned};
+
+/// Flag `init` of singleton PDAs (constant-only seeds) that any signer can
+/// call: whoever front-runs the deploy-time initialization owns the account.
+pub fn check_global_init(item_struct: &ItemStruct, file: &str) {
+    let Fields::Named(fields) = &item_struct.fields else {
+        return;
+    };
+
+    let mut guarded = false;
+    let mut globals = Vec::new();
+    let no_locals = HashMap::new();
+    for field in &fields.named {
+        let constraints = account_constraints(field);
+        let is_signer = account_type(&field.ty).is_some_and(|(wrapper, _)| wrapper == "Signer");
+        if is_signer && find_constraint(&constraints, "address").is_some() {
+            guarded = true;
+        }
+        for value in constraints
+            .iter()
+            .filter(|c| c.key == "constraint")
+            .filter_map(|c| c.value.as_ref())
+        {
+            if checks_admin(value) {
+                guarded = true;
+            }
+        }
+
+        if find_constraint(&constraints, "init").is_none() {
+            continue;
+        }
+        if let Some(seeds) = constraint_seeds(&constraints)
+            && !seeds.is_empty()
+            && seeds.iter().all(|s| is_constant_seed(s, &no_locals, 0))
+        {
+            let rendered: Vec<String> = seeds
+                .iter()
+                .map(|s| s.to_token_stream().to_string())
+                .collect();
+            globals.push((
+                field.ident.as_ref().unwrap().to_string(),
+                rendered.join(", "),
+                seeds[0].span().start().line,
+            ));
+        }
+    }
+    if guarded {
+        return;
+    }
+
+    for (name, seeds, line) in globals {
+        println!(
+            "{} `{}` in struct `{}` is a global PDA (seeds = [{}]) created with `init` by any signer; whoever calls first controls it. \
+Restrict the signer to the upgrade authority via `ProgramData` or to a hard-coded admin key. ({}:{})\n",
+            "[WARNING]".yellow().bold(),
+            name,
+            item_struct.ident,
+            seeds,
+            file,
+            line
+        );
+    }
+}
+
+/// `program_data.upgrade_authority_address == Some(..)` or a comparison
+/// against a constant key such as `ADMIN` or `pubkey!("...")`.
+fn checks_admin(value: &Expr) -> bool {
+    let mut found = false;
+    walk_expr(value, &mut |e: &Expr| match e {
+        Expr::Field(f) if f.member.to_token_stream().to_string() == "upgrade_authority_address" => {
+            found = true;
+        }
+        Expr::Binary(bin)
+            if matches!(bin.op, BinOp::Eq(_))
+                && (is_constant_key(&bin.left) || is_constant_key(&bin.right)) =>
+        {
+            found = true;
+        }
+        _ => {}
+    });
+    found
+}
+
+fn is_constant_key(expr: &Expr) -> bool {
+    match expr {
+        Expr::Macro(m) => m.mac.path.is_ident("pubkey"),
+        Expr::Reference(r) => is_constant_key(&r.expr),
+        Expr::Path(p) => {
+            let last = p.path.segments.last().unwrap().ident.to_string();
+            last != "ID"
+                && last
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+        }
+        _ => false,
+    }
+}
@@ -1,36 +1,194 @@
+use crate::utils::{
+    account_constraints, account_ref, account_type, collect_locals, context_struct_name,
+    find_constraint, int_lit,
+};
+use crate::visit::{ExprVisitor, walk_expr, walk_fn};
 use colored::*;
-use syn::{Error, Fields, ItemStruct, spanned::Spanned};
-
-/// Warn on any `#[account(init_if_needed, ...)]` usage
-pub fn check_init_if_needed(item_struct: &ItemStruct, file: &str) {
-    if let Fields::Named(fields) = &item_struct.fields {
-        for field in &fields.named {
-            for attr in &field.attrs {
-                if attr.path().is_ident("account") {
-                    let mut found = false;
-                    let _ = attr.parse_nested_meta(|meta| {
-                        if meta.path.is_ident("init_if_needed") {
-                            found = true;
-                            Err(Error::new_spanned(meta.path.clone(), "found"))
-                        } else {
-                            Ok(())
+use std::collections::HashMap;
+use syn::{
+    BinOp, Expr, Fields, ItemFn, ItemStruct, Macro, Member, Token, punctuated::Punctuated,
+    spanned::Spanned,
+};
+
+/// Fields whose presence in a check shows the handler tells a fresh account
+/// from an existing one.
+const GUARD_FIELDS: &[&str] = &["is_initialized", "initialized", "bump"];
+
+/// Grade `#[account(init_if_needed, ...)]` fields: token accounts and ATAs are
+/// generally safe, program state is only safe when the handlers using the
+/// struct guard against reinitialization.
+pub fn check_init_if_needed(item_struct: &ItemStruct, file: &str, fns: &[&ItemFn]) {
+    let Fields::Named(fields) = &item_struct.fields else {
+        return;
+    };
+    let struct_name = item_struct.ident.to_string();
+    let handlers: Vec<&ItemFn> = fns
+        .iter()
+        .filter(|f| context_struct_name(f).as_ref() == Some(&struct_name))
+        .copied()
+        .collect();
+
+    for field in &fields.named {
+        let constraints = account_constraints(field);
+        if find_constraint(&constraints, "init_if_needed").is_none() {
+            continue;
+        }
+        let field_name = field.ident.as_ref().unwrap().to_string();
+        let line = field
+            .attrs
+            .first()
+            .map_or_else(|| field.span().start().line, |a| a.span().start().line);
+
+        let is_token = account_type(&field.ty)
+            .is_some_and(|(_, inner)| matches!(inner.as_deref(), Some("TokenAccount" | "Mint")))
+            || constraints.iter().any(|c| {
+                c.key.starts_with("token::")
+                    || c.key.starts_with("associated_token::")
+                    || c.key.starts_with("mint::")
+            });
+        if is_token {
+            println!(
+                "{} `init_if_needed` on token account `{}` in struct `{}`; Anchor validates its mint and authority on reuse, which is generally safe. ({}:{})\n",
+                "[INFO]".cyan().bold(),
+                field_name,
+                struct_name,
+                file,
+                line
+            );
+            continue;
+        }
+
+        if handlers.is_empty() {
+            println!(
+                "{} `init_if_needed` on `{}` in struct `{}` may reinitialize an existing account. Use with caution! ({}:{})\n",
+                "[WARNING]".yellow().bold(),
+                field_name,
+                struct_name,
+                file,
+                line
+            );
+            continue;
+        }
+
+        let unguarded: Vec<String> = handlers
+            .iter()
+            .filter(|f| !guards_reinit(f, &field_name))
+            .map(|f| format!("`{}`", f.sig.ident))
+            .collect();
+        if unguarded.is_empty() {
+            println!(
+                "{} `init_if_needed` on `{}` in struct `{}` is guarded against reinitialization by its handlers. ({}:{})\n",
+                "[INFO]".cyan().bold(),
+                field_name,
+                struct_name,
+                file,
+                line
+            );
+        } else {
+            println!(
+                "{} `init_if_needed` on program state `{}` in struct `{}` is not checked for prior initialization in {}; \
+an existing account can be reset. Check an `is_initialized` flag or `bump != 0` before writing. ({}:{})\n",
+                "[ERROR]".red().bold(),
+                field_name,
+                struct_name,
+                unguarded.join(", "),
+                file,
+                line
+            );
+        }
+    }
+}
+
+/// Whether `func` tests an initialization marker of `account` in a condition
+/// (`if`/`while`, `require!`/`assert!`) or compares one of its fields with a
+/// guard value or default before writing it. Writes such as
+/// `state.is_initialized = true` do not count.
+fn guards_reinit(func: &ItemFn, account: &str) -> bool {
+    struct Guards<'a> {
+        account: &'a str,
+        locals: HashMap<String, Expr>,
+        guarded: bool,
+    }
+
+    impl Guards<'_> {
+        fn field_of_account(&self, e: &Expr) -> Option<String> {
+            match e {
+                Expr::Field(f) => match &f.member {
+                    Member::Named(m)
+                        if account_ref(&f.base, &self.locals).as_deref() == Some(self.account) =>
+                    {
+                        Some(m.to_string())
+                    }
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+
+        /// `bump` only counts when compared, a bare read proves nothing
+        fn check_condition(&mut self, cond: &Expr) {
+            let mut found = false;
+            walk_expr(cond, &mut |e: &Expr| {
+                if self
+                    .field_of_account(e)
+                    .is_some_and(|m| m != "bump" && GUARD_FIELDS.contains(&m.as_str()))
+                {
+                    found = true;
+                }
+            });
+            self.guarded |= found;
+        }
+    }
+
+    impl ExprVisitor for Guards<'_> {
+        fn visit_expr(&mut self, expr: &Expr) {
+            match expr {
+                Expr::If(e) => self.check_condition(&e.cond),
+                Expr::While(e) => self.check_condition(&e.cond),
+                Expr::Binary(bin) if matches!(bin.op, BinOp::Eq(_) | BinOp::Ne(_)) => {
+                    for (side, other) in [(&bin.left, &bin.right), (&bin.right, &bin.left)] {
+                        if let Some(m) = self.field_of_account(side)
+                            && (GUARD_FIELDS.contains(&m.as_str()) || is_default(other))
+                        {
+                            self.guarded = true;
                         }
-                    });
-
-                    if found {
-                        let field_name = field.ident.as_ref().unwrap();
-                        let line = attr.span().start().line;
-                        println!(
-                            "{} `init_if_needed` on `{}` in struct `{}` may reinitialize an existing account. Use with caution! ({}:{})\n",
-                            "[WARNING]".yellow().bold(),
-                            field_name,
-                            item_struct.ident,
-                            file,
-                            line
-                        );
                     }
                 }
+                _ => {}
             }
         }
+
+        fn visit_macro(&mut self, mac: &Macro) {
+            let is_check = mac
+                .path
+                .segments
+                .last()
+                .is_some_and(|s| s.ident == "require" || s.ident == "assert");
+            if is_check
+                && let Ok(args) =
+                    mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+                && let Some(cond) = args.first()
+            {
+                self.check_condition(cond);
+            }
+        }
+    }
+
+    let mut guards = Guards {
+        account,
+        locals: collect_locals(func),
+        guarded: false,
+    };
+    walk_fn(func, &mut guards);
+    guards.guarded
+}
+
+/// `0`, `Default::default()` or `Pubkey::default()`.
+fn is_default(expr: &Expr) -> bool {
+    match expr {
+        Expr::Call(c) => {
+            matches!(&*c.func, Expr::Path(p) if p.path.segments.last().unwrap().ident == "default")
+        }
+        expr => int_lit(expr) == Some(0),
     }
 }
@@ -0,0 +1,220 @@
+use crate::checks::sysvar_check::is_unchecked_account;
+use crate::utils::{
+    AccountsStructMap, accounts_in, collect_locals, context_struct_name, find_field,
+};
+use crate::visit::{ExprVisitor, walk_expr, walk_fn};
+use colored::*;
+use std::collections::HashMap;
+use syn::{BinOp, Expr, ItemFn, Macro, Member, Token, punctuated::Punctuated, spanned::Spanned};
+
+/// Flag deprecated instructions-sysvar readers and signature-verification
+/// flows that do not validate the instruction they introspect.
+pub fn check_instruction_introspection(
+    func: &ItemFn,
+    file: &str,
+    accounts_structs: &AccountsStructMap,
+) {
+    let locals = collect_locals(func);
+    let item_struct = context_struct_name(func).and_then(|n| accounts_structs.get(&n).copied());
+    let fn_name = func.sig.ident.to_string();
+
+    let mut loads = Vec::new();
+    let mut sig_program = false;
+
+    walk_fn(func, &mut |expr: &Expr| match expr {
+        Expr::Call(call) => {
+            let Expr::Path(p) = &*call.func else {
+                return;
+            };
+            let name = p.path.segments.last().unwrap().ident.to_string();
+            let line = expr.span().start().line;
+            match name.as_str() {
+                // the unchecked variants take raw data and never verify the sysvar id
+                "load_instruction_at" | "load_current_index" => {
+                    let accounts: Vec<String> = call
+                        .args
+                        .iter()
+                        .flat_map(|a| accounts_in(a, &locals))
+                        .collect();
+                    let unchecked = accounts.is_empty()
+                        || accounts.iter().any(|a| {
+                            item_struct
+                                .and_then(|s| find_field(s, a))
+                                .is_none_or(is_unchecked_account)
+                        });
+                    let (level, risk) = if unchecked {
+                        (
+                            "[ERROR]".red().bold(),
+                            "on an instructions account whose address is not checked, so a fake sysvar can be passed",
+                        )
+                    } else {
+                        ("[WARNING]".yellow().bold(), "which is deprecated")
+                    };
+                    println!(
+                        "{} `{}` is used in `{}` {}. Use `{}_checked` with `address = sysvar::instructions::ID`. ({}:{})\n",
+                        level, name, fn_name, risk, name, file, line
+                    );
+                    loads.push(line);
+                }
+                "load_instruction_at_checked" | "get_instruction_relative" => loads.push(line),
+                _ => {}
+            }
+            if path_mentions_sig_program(&p.path) {
+                sig_program = true;
+            }
+        }
+        Expr::Path(p) => sig_program |= path_mentions_sig_program(&p.path),
+        _ => {}
+    });
+
+    if !sig_program || loads.is_empty() {
+        return;
+    }
+
+    let mut checks = Checks {
+        locals: &locals,
+        program_id: false,
+        offsets: false,
+        message: false,
+    };
+    walk_fn(func, &mut checks);
+
+    let mut missing = Vec::new();
+    if !checks.program_id {
+        missing.push("the program id");
+    }
+    if !checks.offsets {
+        missing.push("the signature offsets");
+    }
+    if !checks.message {
+        missing.push("the signed message and signer");
+    }
+    if missing.is_empty() {
+        return;
+    }
+
+    println!(
+        "{} `{}` verifies an Ed25519/Secp256k1 signature by introspecting another instruction but does not check {}. \
+An attacker can point it at an unrelated instruction. ({}:{})\n",
+        "[WARNING]".yellow().bold(),
+        fn_name,
+        missing.join(", "),
+        file,
+        loads[0]
+    );
+}
+
+fn path_mentions_sig_program(path: &syn::Path) -> bool {
+    path.segments.iter().any(|s| {
+        let s = s.ident.to_string().to_lowercase();
+        s.contains("ed25519") || s.contains("secp256k1")
+    })
+}
+
+/// What a handler compares against the instruction it loaded.
+struct Checks<'a> {
+    locals: &'a HashMap<String, Expr>,
+    program_id: bool,
+    offsets: bool,
+    message: bool,
+}
+
+impl Checks<'_> {
+    /// Notes the names in a comparison or condition, but only when one side
+    /// reads the loaded instruction; naming a variable `offset` proves nothing.
+    fn compared(&mut self, sides: &[&Expr]) {
+        if !sides
+            .iter()
+            .any(|side| from_loaded_instruction(side, self.locals, 0))
+        {
+            return;
+        }
+        for side in sides {
+            walk_expr(side, &mut |e: &Expr| {
+                let name = match e {
+                    Expr::Path(p) => p.path.get_ident().map(|i| i.to_string()),
+                    Expr::Field(f) => match &f.member {
+                        Member::Named(m) => Some(m.to_string()),
+                        Member::Unnamed(_) => None,
+                    },
+                    Expr::MethodCall(mc) => Some(mc.method.to_string()),
+                    _ => None,
+                };
+                if let Some(name) = name {
+                    self.program_id |= name == "program_id";
+                    note_ident(&name, &mut self.offsets, &mut self.message);
+                }
+            });
+        }
+    }
+}
+
+impl ExprVisitor for Checks<'_> {
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Binary(b) if matches!(b.op, BinOp::Eq(_) | BinOp::Ne(_)) => {
+                self.compared(&[&b.left, &b.right]);
+            }
+            Expr::If(e) => self.compared(&[&e.cond]),
+            _ => {}
+        }
+    }
+
+    fn visit_macro(&mut self, mac: &Macro) {
+        let Some(name) = mac.path.get_ident().map(|i| i.to_string()) else {
+            return;
+        };
+        let compared = match name.as_str() {
+            "require" | "assert" => 1,
+            "require_eq" | "require_neq" | "require_keys_eq" | "require_keys_neq" | "assert_eq"
+            | "assert_ne" => 2,
+            _ => return,
+        };
+        if let Ok(args) = mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated) {
+            let sides: Vec<&Expr> = args.iter().take(compared).collect();
+            self.compared(&sides);
+        }
+    }
+}
+
+/// Whether `expr`, following locals, reads an instruction loaded from the
+/// instructions sysvar, e.g. `ix.data[..2]` after `let ix = load_instruction_at_checked(..)?`.
+fn from_loaded_instruction(expr: &Expr, locals: &HashMap<String, Expr>, depth: usize) -> bool {
+    if depth > 8 {
+        return false;
+    }
+    let mut found = false;
+    walk_expr(expr, &mut |e: &Expr| match e {
+        Expr::Call(call) => {
+            if let Expr::Path(p) = &*call.func
+                && p.path.segments.last().is_some_and(|s| {
+                    matches!(
+                        s.ident.to_string().as_str(),
+                        "load_instruction_at"
+                            | "load_instruction_at_checked"
+                            | "get_instruction_relative"
+                    )
+                })
+            {
+                found = true;
+            }
+        }
+        Expr::Path(p) => {
+            if let Some(init) = p.path.get_ident().and_then(|i| locals.get(&i.to_string())) {
+                found |= from_loaded_instruction(init, locals, depth + 1);
+            }
+        }
+        _ => {}
+    });
+    found
+}
+
+fn note_ident(name: &str, offsets: &mut bool, message: &mut bool) {
+    let name = name.to_lowercase();
+    if name.contains("offset") {
+        *offsets = true;
+    }
+    if name.contains("message") || name.contains("pubkey") {
+        *message = true;
+    }
+}
@@ -0,0 +1,236 @@
+use crate::checks::cpi_check::{CpiResolver, cpi_account_field, cpi_context_parts};
+use crate::utils::{
+    AccountsStructMap, LamportChange, account_constraints, account_info_ref, account_type,
+    collect_locals, context_struct_name, find_constraint, find_field, lamport_write,
+};
+use crate::visit::walk_fn;
+use colored::*;
+use quote::ToTokens;
+use std::collections::BTreeMap;
+use syn::{Expr, ItemFn, ItemStruct, spanned::Spanned};
+
+/// Wrappers whose account holds program data and so cannot be the `from` of a
+/// System Program transfer.
+const DATA_WRAPPERS: &[&str] = &["Account", "AccountLoader", "InterfaceAccount"];
+
+struct LamportMove {
+    account: String,
+    amount: String,
+    line: usize,
+}
+
+/// Direct lamport arithmetic in a handler: debits from accounts the program
+/// does not own, debits and credits that don't add up, debits that ignore rent
+/// exemption, and `system_program::transfer` out of accounts that carry data.
+pub fn check_lamport_transfers(
+    func: &ItemFn,
+    file: &str,
+    accounts_structs: &AccountsStructMap,
+    cpi: &CpiResolver,
+) {
+    let Some(item_struct) =
+        context_struct_name(func).and_then(|n| accounts_structs.get(&n).copied())
+    else {
+        return;
+    };
+    let locals = collect_locals(func);
+    let fn_name = func.sig.ident.to_string();
+
+    let mut debits = Vec::new();
+    let mut credits = Vec::new();
+    let mut closes = false;
+    let mut rent_checked = false;
+    walk_fn(func, &mut |expr: &Expr| {
+        if let Some(write) = lamport_write(expr, &locals) {
+            let line = expr.span().start().line;
+            match write.change {
+                // a full drain is a close, which close_check covers
+                LamportChange::Drain => closes = true,
+                LamportChange::Debit(amount) => debits.push(LamportMove {
+                    account: write.account,
+                    amount: normalize(amount),
+                    line,
+                }),
+                LamportChange::Credit(amount) => credits.push(LamportMove {
+                    account: write.account,
+                    amount: normalize(amount),
+                    line,
+                }),
+                LamportChange::Set => {}
+            }
+        } else if let Expr::MethodCall(mc) = expr
+            && mc.method == "minimum_balance"
+        {
+            rent_checked = true;
+        }
+    });
+
+    for debit in &debits {
+        report_unowned_debit(item_struct, &fn_name, debit, file);
+    }
+
+    // a close moves the whole balance, so amounts cannot be matched up
+    if !closes && !balanced(&debits, &credits) {
+        let side = |moves: &[LamportMove]| {
+            if moves.is_empty() {
+                "nothing".to_string()
+            } else {
+                moves
+                    .iter()
+                    .map(|m| format!("`{}` from `{}`", m.amount, m.account))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }
+        };
+        let line = debits.iter().chain(&credits).map(|m| m.line).min().unwrap();
+        println!(
+            "{} Lamport debits and credits in `{}` don't balance: debited {}, credited {}. \
+The runtime rejects instructions that create or destroy lamports. ({}:{})\n",
+            "[WARNING]".yellow().bold(),
+            fn_name,
+            side(&debits),
+            side(&credits).replace(" from ", " to "),
+            file,
+            line
+        );
+    }
+
+    if !rent_checked {
+        let mut seen = Vec::new();
+        for debit in &debits {
+            if seen.contains(&debit.account) {
+                continue;
+            }
+            seen.push(debit.account.clone());
+            println!(
+                "{} `{}` is debited in `{}` without comparing its remaining balance to `Rent::get()?.minimum_balance(..)`; \
+a large enough `{}` leaves it below rent exemption and the transaction fails. ({}:{})\n",
+                "[WARNING]".yellow().bold(),
+                debit.account,
+                fn_name,
+                debit.amount,
+                file,
+                debit.line
+            );
+        }
+    }
+
+    check_system_transfers(func, file, item_struct, cpi);
+}
+
+/// Only the owning program may debit an account, so a debit from a system-
+/// or token-owned account always fails, and one from an unchecked account
+/// fails unless its owner is verified.
+fn report_unowned_debit(item_struct: &ItemStruct, fn_name: &str, debit: &LamportMove, file: &str) {
+    let Some(field) = find_field(item_struct, &debit.account) else {
+        return;
+    };
+    let Some((wrapper, inner)) = account_type(&field.ty) else {
+        return;
+    };
+    let owner = match (wrapper.as_str(), inner.as_deref()) {
+        ("Signer" | "SystemAccount", _) => "the System Program",
+        (_, Some("TokenAccount" | "Mint")) => "the Token Program",
+        ("UncheckedAccount" | "AccountInfo", _) => {
+            let cs = account_constraints(field);
+            if find_constraint(&cs, "owner").is_none() {
+                println!(
+                    "{} `{}` is debited directly in `{}` but is `{}` with no `owner` constraint; the debit only succeeds if this program owns it. \
+Add `owner = crate::ID` or use a typed `Account`. ({}:{})\n",
+                    "[WARNING]".yellow().bold(),
+                    debit.account,
+                    fn_name,
+                    wrapper,
+                    file,
+                    debit.line
+                );
+            }
+            return;
+        }
+        _ => return,
+    };
+    println!(
+        "{} `{}` is debited directly in `{}` but is owned by {}; only the owning program can subtract lamports from an account. \
+Move the lamports with a CPI to the owning program instead. ({}:{})\n",
+        "[ERROR]".red().bold(),
+        debit.account,
+        fn_name,
+        owner,
+        file,
+        debit.line
+    );
+}
+
+/// `system_program::transfer` requires a `from` that is system-owned and
+/// holds no data, which a program account never is.
+fn check_system_transfers(func: &ItemFn, file: &str, item_struct: &ItemStruct, cpi: &CpiResolver) {
+    let locals = collect_locals(func);
+    let fn_name = func.sig.ident.to_string();
+    walk_fn(func, &mut |expr: &Expr| {
+        let Expr::Call(call) = expr else {
+            return;
+        };
+        if cpi.cpi_name(call, &locals).as_deref() != Some("system_program::transfer") {
+            return;
+        }
+        let Some(from) = call
+            .args
+            .first()
+            .and_then(|a| cpi_context_parts(a, &locals))
+            .and_then(|parts| parts.accounts)
+            .and_then(|accounts| {
+                cpi_account_field(&accounts, "from").and_then(|e| account_info_ref(e, &locals))
+            })
+        else {
+            return;
+        };
+        let Some(field) = find_field(item_struct, &from) else {
+            return;
+        };
+        let Some((wrapper, Some(inner))) = account_type(&field.ty) else {
+            return;
+        };
+        if !DATA_WRAPPERS.contains(&wrapper.as_str()) {
+            return;
+        }
+        let kind = if find_constraint(&account_constraints(field), "seeds").is_some() {
+            "PDA"
+        } else {
+            "account"
+        };
+        println!(
+            "{} `system_program::transfer` in `{}` sends from {} `{}` (`{}<{}>`), which carries data; the System Program rejects transfers from accounts with data. \
+Debit its lamports directly with `try_borrow_mut_lamports` instead. ({}:{})\n",
+            "[ERROR]".red().bold(),
+            fn_name,
+            kind,
+            from,
+            wrapper,
+            inner,
+            file,
+            call.span().start().line
+        );
+    });
+}
+
+/// Whether every debited amount is credited somewhere and vice versa.
+fn balanced(debits: &[LamportMove], credits: &[LamportMove]) -> bool {
+    let mut counts: BTreeMap<&str, i64> = BTreeMap::new();
+    for d in debits {
+        *counts.entry(&d.amount).or_default() += 1;
+    }
+    for c in credits {
+        *counts.entry(&c.amount).or_default() -= 1;
+    }
+    counts.values().all(|n| *n == 0)
+}
+
+/// Token text of an amount with references and parentheses stripped, so
+/// `amount`, `(amount)` and `&amount` compare equal.
+fn normalize(expr: &Expr) -> String {
+    match expr {
+        Expr::Paren(p) => normalize(&p.expr),
+        Expr::Reference(r) => normalize(&r.expr),
+        _ => expr.to_token_stream().to_string(),
+    }
+}
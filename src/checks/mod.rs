@@ -7,3 +7,20 @@ pub mod init_if_needed_check;
 pub mod seeds_reuse_check;
 pub use seeds_reuse_check::GlobalSeedUsage;
 pub mod mut_borrow_check;
+pub mod reload_check;
+pub mod signer_seeds_check;
+pub mod bump_check;
+pub mod type_cosplay_check;
+pub mod close_check;
+pub mod arithmetic_check;
+pub mod precision_check;
+pub mod sysvar_check;
+pub mod introspection_check;
+pub mod init_frontrun_check;
+pub mod space_check;
+pub mod collection_check;
+pub mod zero_copy_check;
+pub mod token_check;
+pub mod lamports_check;
+pub mod oracle_check;
+pub mod access_control_check;
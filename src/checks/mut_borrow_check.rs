@@ -1,9 +1,10 @@
+use crate::utils::{context_struct_name, lamport_write};
 use colored::*;
 use std::collections::{HashMap, HashSet};
 use syn::{
     Attribute, Error, Expr, ExprAssign, ExprBlock, ExprField, ExprForLoop, ExprIf, ExprLoop,
-    ExprMatch, ExprPath, ExprReference, ExprWhile, FnArg, GenericArgument, ItemFn,
-    LocalInit, Member, PathArguments, Stmt, Type, spanned::Spanned, ExprBinary, BinOp
+    ExprMatch, ExprPath, ExprReference, ExprWhile, ItemFn, LocalInit, Member, Stmt,
+    spanned::Spanned, ExprBinary, BinOp
 };
 
 pub type AccountsMutMap = HashMap<String, HashSet<String>>;
@@ -32,28 +33,7 @@ pub fn attr_contains_mut(attr: &Attribute) -> bool {
 
 pub fn check_mut_borrow(func: &ItemFn, file: &str, accounts_mut: &AccountsMutMap, accounts_init: &AccountsInitMap) {
     // Determine which Accounts struct the Context<> refers to
-    let ctx_struct = func
-        .sig
-        .inputs
-        .iter()
-        .find_map(|arg| {
-            if let FnArg::Typed(pat_ty) = arg {
-                if let Type::Path(tp) = &*pat_ty.ty {
-                    if tp.path.segments.last()?.ident == "Context" {
-                        if let PathArguments::AngleBracketed(ab) =
-                            &tp.path.segments.last()?.arguments
-                        {
-                            if let Some(GenericArgument::Type(Type::Path(inner))) = ab.args.first()
-                            {
-                                return Some(inner.path.segments.last()?.ident.to_string());
-                            }
-                        }
-                    }
-                }
-            }
-            None
-        })
-        .unwrap_or_default();
+    let ctx_struct = context_struct_name(func).unwrap_or_default();
 
     let mut_set = accounts_mut.get(&ctx_struct).cloned().unwrap_or_default();
     let init_set = accounts_init.get(&ctx_struct).cloned().unwrap_or_default();
@@ -124,6 +104,7 @@ Please add `#[account(mut)]` to `{}`. ({}:{})\n",
         // assignment ctx.accounts.foo.<...> = ...
         Expr::Assign(ExprAssign { left, right, .. }) => {
             detect_field_mutation(left, file, fn_name, ctx_struct, mut_set, init_set);
+            detect_lamport_mutation(expr, file, fn_name, ctx_struct, mut_set, init_set);
             detect_expr(right, file, fn_name, ctx_struct, mut_set, init_set);
         }
 
@@ -136,6 +117,7 @@ Please add `#[account(mut)]` to `{}`. ({}:{})\n",
                 | BinOp::RemAssign(_) => {
                     // treat it like an assignment to the left side
                     detect_field_mutation(left, file, fn_name, ctx_struct, mut_set, init_set);
+                    detect_lamport_mutation(expr, file, fn_name, ctx_struct, mut_set, init_set);
                 }
                 _ => {}
             }
@@ -157,6 +139,26 @@ Please add `#[account(mut)]` to `{}`. ({}:{})\n",
         }
 
         Expr::MethodCall(mc) => {
+            // ctx.accounts.foo.load_mut() on an AccountLoader
+            if mc.method == "load_mut"
+                && let Expr::Field(f) = &*mc.receiver
+                && let Some(field_name) = extract_account_field(f)
+                && !mut_set.contains(&field_name)
+                && !init_set.contains(&field_name)
+            {
+                let line = mc.method.span().start().line;
+                println!(
+                    "{} `load_mut()` is called on `{}` in `{}` but it is not declared `mut` in `{}`; the changes are never written back. \
+Please add `#[account(mut)]` to `{}`. ({}:{})\n",
+                    "[ERROR]".red().bold(),
+                    field_name,
+                    fn_name,
+                    ctx_struct,
+                    field_name,
+                    file,
+                    line
+                );
+            }
             detect_expr(&mc.receiver, file, fn_name, ctx_struct, mut_set, init_set);
             for arg in &mc.args {
                 detect_expr(arg, file, fn_name, ctx_struct, mut_set, init_set);
@@ -265,6 +267,36 @@ Please add `#[account(mut)]` to `{}`. ({}:{})\n",
     }
 }
 
+/// Handle `**ctx.accounts.foo.try_borrow_mut_lamports()? -= ...`; lamport
+/// changes to an account not marked writable fail at runtime.
+fn detect_lamport_mutation(
+    expr: &Expr,
+    file: &str,
+    fn_name: &str,
+    ctx_struct: &str,
+    mut_set: &HashSet<String>,
+    init_set: &HashSet<String>,
+) {
+    let Some(acct_name) = lamport_write(expr, &HashMap::new()).map(|w| w.account) else {
+        return;
+    };
+    if mut_set.contains(&acct_name) || init_set.contains(&acct_name) {
+        return;
+    }
+    let line = expr.span().start().line;
+    println!(
+        "{} Lamports of `{}` are modified in `{}` but it is not declared `mut` in `{}`. \
+Please add `#[account(mut)]` to `{}`. ({}:{})\n",
+        "[ERROR]".red().bold(),
+        acct_name,
+        fn_name,
+        ctx_struct,
+        acct_name,
+        file,
+        line
+    );
+}
+
 /// If f represents `ctx.accounts.foo`, return Some("foo").
 fn extract_account_field(f: &ExprField) -> Option<String> {
     if let Expr::Field(inner) = &*f.base {
@@ -0,0 +1,230 @@
+use crate::index::AccountIndex;
+use crate::utils::{
+    AccountsStructMap, account_constraints, account_info_ref, account_type, collect_locals,
+    context_struct_name, find_constraint, find_field,
+};
+use crate::visit::walk_fn;
+use colored::*;
+use std::collections::HashMap;
+use syn::{Expr, Field, Fields, ItemFn, ItemStruct, Member, spanned::Spanned};
+
+/// Longest `get_price_no_older_than` window, in seconds, not reported as stale.
+const MAX_PRICE_AGE_SECS: u128 = 300;
+
+/// Pyth readers that return a price regardless of when it was published.
+const UNCHECKED_READERS: &[&str] = &[
+    "get_price_unchecked",
+    "get_ema_price_unchecked",
+    "get_current_price",
+    "get_current_ema_price",
+];
+
+/// Pyth readers that take a maximum age as their second argument.
+const AGED_READERS: &[&str] = &["get_price_no_older_than", "get_ema_price_no_older_than"];
+
+/// Functions that deserialize a price feed from a raw account.
+const FEED_LOADERS: &[&str] = &["load_price_feed_from_account_info", "account_info_to_feed"];
+
+/// Types whose `new`/`parse` is a feed loader.
+const FEED_TYPES: &[&str] = &[
+    "SolanaPriceAccount",
+    "AggregatorAccountData",
+    "PullFeedAccountData",
+];
+
+/// Whether a field name is that of an oracle feed (`oracle`, `price_feed`,
+/// `sol_price_update`, `pyth_account`, `btc_aggregator`, ...). Names that only
+/// contain such a word, like `price_authority` or `feed_receiver`, don't match.
+pub fn looks_like_oracle(name: &str) -> bool {
+    matches!(
+        name,
+        "oracle" | "feed" | "price_feed" | "price_update" | "aggregator"
+    ) || ["_oracle", "_price_feed", "_price_update", "_aggregator"]
+        .iter()
+        .any(|suffix| name.ends_with(suffix))
+        || ["oracle_", "pyth_", "switchboard_"]
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+}
+
+/// Whether `field` is a raw `AccountInfo`/`UncheckedAccount` that nothing
+/// ties to a known feed: no `address`, `owner` or `constraint`.
+fn is_unpinned_feed(field: &Field) -> bool {
+    let raw = account_type(&field.ty)
+        .is_some_and(|(wrapper, _)| wrapper == "AccountInfo" || wrapper == "UncheckedAccount");
+    let cs = account_constraints(field);
+    raw && ["address", "owner", "constraint"]
+        .iter()
+        .all(|key| find_constraint(&cs, key).is_none())
+}
+
+/// Flag oracle-named fields that accept any account.
+pub fn check_oracle_accounts(item_struct: &ItemStruct, file: &str) {
+    let Fields::Named(fields) = &item_struct.fields else {
+        return;
+    };
+    for field in &fields.named {
+        let name = field.ident.as_ref().unwrap();
+        if looks_like_oracle(&name.to_string()) && is_unpinned_feed(field) {
+            println!(
+                "{} `{}` in struct `{}` looks like an oracle price account but is unchecked with no `address`, `owner` or feed constraint; \
+an attacker can pass an account with a price of their choosing. Pin it with `address = ...` or use a typed `Account<'info, PriceUpdateV2>` and check the feed id. ({}:{})\n",
+                "[ERROR]".red().bold(),
+                name,
+                item_struct.ident,
+                file,
+                name.span().start().line
+            );
+        }
+    }
+}
+
+/// Flag oracle reads in a handler that skip staleness checks, accept a very
+/// old price, ignore the confidence interval, or load a feed from an
+/// unchecked account.
+pub fn check_oracle_usage(
+    func: &ItemFn,
+    file: &str,
+    accounts_structs: &AccountsStructMap,
+    index: &AccountIndex,
+) {
+    let locals = collect_locals(func);
+    let item_struct = context_struct_name(func).and_then(|n| accounts_structs.get(&n).copied());
+    let fn_name = func.sig.ident.to_string();
+
+    let mut first_read = None;
+    let mut switchboard_read = None;
+    let mut staleness_checked = false;
+    let mut confidence_checked = false;
+    walk_fn(func, &mut |expr: &Expr| match expr {
+        Expr::MethodCall(mc) => {
+            let method = mc.method.to_string();
+            let line = mc.method.span().start().line;
+            if UNCHECKED_READERS.contains(&method.as_str()) {
+                first_read.get_or_insert(line);
+                println!(
+                    "{} `{}` in `{}` returns the last published price however old it is. \
+Use `get_price_no_older_than` with a short maximum age. ({}:{})\n",
+                    "[WARNING]".yellow().bold(),
+                    method,
+                    fn_name,
+                    file,
+                    line
+                );
+            } else if AGED_READERS.contains(&method.as_str()) {
+                first_read.get_or_insert(line);
+                let age = mc.args.iter().nth(1).and_then(|a| eval(a, &locals, index));
+                if let Some(age) = age
+                    && age > MAX_PRICE_AGE_SECS
+                {
+                    println!(
+                        "{} `{}` in `{}` accepts prices up to {} seconds old; a price that stale can be far from the market. \
+Use a window of at most {} seconds. ({}:{})\n",
+                        "[WARNING]".yellow().bold(),
+                        method,
+                        fn_name,
+                        age,
+                        MAX_PRICE_AGE_SECS,
+                        file,
+                        line
+                    );
+                }
+            } else if method == "get_result" {
+                // Switchboard aggregator
+                first_read.get_or_insert(line);
+                switchboard_read.get_or_insert(line);
+            } else if method == "check_staleness" {
+                staleness_checked = true;
+            } else if matches!(
+                method.as_str(),
+                "check_confidence_interval" | "std_deviation" | "get_confidence"
+            ) {
+                confidence_checked = true;
+            }
+        }
+        Expr::Field(f) => {
+            if let Member::Named(m) = &f.member
+                && (m == "conf" || m == "std_deviation")
+            {
+                confidence_checked = true;
+            } else if let Member::Named(m) = &f.member
+                && m == "round_open_timestamp"
+            {
+                staleness_checked = true;
+            }
+        }
+        Expr::Call(call) => {
+            let Expr::Path(p) = &*call.func else {
+                return;
+            };
+            let segs: Vec<String> = p
+                .path
+                .segments
+                .iter()
+                .map(|s| s.ident.to_string())
+                .collect();
+            let is_loader = match segs.as_slice() {
+                [.., ty, f] if f == "new" || f == "parse" => FEED_TYPES.contains(&ty.as_str()),
+                [.., f] => FEED_LOADERS.contains(&f.as_str()),
+                [] => false,
+            };
+            if !is_loader {
+                return;
+            }
+            let Some(account) = call.args.first().and_then(|a| account_info_ref(a, &locals)) else {
+                return;
+            };
+            let Some(field) = item_struct.and_then(|s| find_field(s, &account)) else {
+                return;
+            };
+            // oracle-named fields are already reported by check_oracle_accounts
+            if is_unpinned_feed(field) && !looks_like_oracle(&account) {
+                println!(
+                    "{} `{}` in `{}` loads a price feed from `{}`, an unchecked account with no `address`, `owner` or feed constraint; \
+any account with a forged price can be passed. ({}:{})\n",
+                    "[ERROR]".red().bold(),
+                    segs.join("::"),
+                    fn_name,
+                    account,
+                    file,
+                    call.span().start().line
+                );
+            }
+        }
+        _ => {}
+    });
+
+    if let Some(line) = switchboard_read
+        && !staleness_checked
+    {
+        println!(
+            "{} Switchboard `get_result` in `{}` is used without `check_staleness` or a `round_open_timestamp` check. ({}:{})\n",
+            "[WARNING]".yellow().bold(),
+            fn_name,
+            file,
+            line
+        );
+    }
+    if let Some(line) = first_read
+        && !confidence_checked
+    {
+        println!(
+            "{} The oracle price read in `{}` never looks at its confidence interval (`conf` / `std_deviation`); \
+during volatile or thin markets the reported price can be far off. Reject prices whose confidence is too wide. ({}:{})\n",
+            "[WARNING]".yellow().bold(),
+            fn_name,
+            file,
+            line
+        );
+    }
+}
+
+/// Constant value of `expr`, following a `let` binding to a literal or const.
+fn eval(expr: &Expr, locals: &HashMap<String, Expr>, index: &AccountIndex) -> Option<u128> {
+    if let Expr::Path(p) = expr
+        && let Some(init) = p.path.get_ident().and_then(|i| locals.get(&i.to_string()))
+    {
+        return index.eval(init);
+    }
+    index.eval(expr)
+}
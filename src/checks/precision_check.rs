@@ -0,0 +1,265 @@
+use crate::checks::arithmetic_check::tainted_source;
+use crate::utils::{collect_locals, instruction_args};
+use crate::visit::{ExprVisitor, walk_fn};
+use colored::*;
+use quote::ToTokens;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use syn::{BinOp, Expr, FnArg, ItemFn, Lit, Local, Pat, Type, spanned::Spanned};
+
+/// Flag truncating `as` casts, floating point and division before
+/// multiplication in handler bodies.
+pub fn check_precision_loss(func: &ItemFn, file: &str) {
+    let fn_name = func.sig.ident.to_string();
+    let locals = collect_locals(func);
+    let args = instruction_args(func);
+
+    let mut scan = PrecisionScan {
+        locals: &locals,
+        args: &args,
+        types: declared_types(func),
+        casts: Vec::new(),
+        float_lines: BTreeSet::new(),
+        div_mul_lines: BTreeSet::new(),
+    };
+    walk_fn(func, &mut scan);
+
+    for (line, source, from, to) in scan.casts {
+        let value = match from {
+            Some(from) => format!("A `{}` value from `{}`", from, source),
+            None => format!("`{}`", source),
+        };
+        println!(
+            "{} {} is cast `as {}` in `{}`, which can silently truncate. Use `{}::try_from(..)` instead. ({}:{})\n",
+            "[WARNING]".yellow().bold(),
+            value,
+            to,
+            fn_name,
+            to,
+            file,
+            line
+        );
+    }
+    for line in scan.float_lines {
+        println!(
+            "{} Floating point used in `{}`; float rounding makes token amounts imprecise and float math is costly on-chain. \
+Use fixed-point integer math. ({}:{})\n",
+            "[WARNING]".yellow().bold(),
+            fn_name,
+            file,
+            line
+        );
+    }
+    for line in scan.div_mul_lines {
+        println!(
+            "{} Division before multiplication in `{}` loses precision (`a / b * c`). \
+Multiply first, then divide. ({}:{})\n",
+            "[WARNING]".yellow().bold(),
+            fn_name,
+            file,
+            line
+        );
+    }
+}
+
+struct PrecisionScan<'a> {
+    locals: &'a HashMap<String, Expr>,
+    args: &'a HashSet<String>,
+    /// declared integer type of arguments and annotated locals
+    types: HashMap<String, String>,
+    casts: Vec<(usize, String, Option<String>, String)>,
+    float_lines: BTreeSet<usize>,
+    div_mul_lines: BTreeSet<usize>,
+}
+
+impl ExprVisitor for PrecisionScan<'_> {
+    fn visit_local(&mut self, local: &Local) {
+        if let Pat::Type(pt) = &local.pat
+            && is_float_type(&pt.ty)
+        {
+            self.float_lines.insert(pt.ty.span().start().line);
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Cast(cast) => {
+                if is_float_type(&cast.ty) {
+                    self.float_lines.insert(cast.ty.span().start().line);
+                    return;
+                }
+                let Some(to) = type_name(&cast.ty).filter(|t| int_bits(t).is_some()) else {
+                    return;
+                };
+                let from = self.int_type(&cast.expr, 0);
+                let lossy = match &from {
+                    Some(from) => is_lossy(from, &to),
+                    // unknown source: only small targets of amounts are suspicious
+                    None => int_bits(&to).is_some_and(|(bits, _)| bits <= 32),
+                };
+                if !lossy {
+                    return;
+                }
+                let source = match &from {
+                    Some(_) => tainted_source(&cast.expr, self.locals, self.args, 0)
+                        .unwrap_or_else(|| cast.expr.to_token_stream().to_string()),
+                    None => match tainted_source(&cast.expr, self.locals, self.args, 0) {
+                        Some(source) => source,
+                        None => return,
+                    },
+                };
+                self.casts
+                    .push((cast.span().start().line, source, from, to));
+            }
+
+            Expr::Lit(lit) if matches!(lit.lit, Lit::Float(_)) => {
+                self.float_lines.insert(lit.span().start().line);
+            }
+
+            Expr::Binary(bin)
+                if matches!(bin.op, BinOp::Mul(_) | BinOp::MulAssign(_))
+                    && (is_division(&bin.left) || is_division(&bin.right)) =>
+            {
+                self.div_mul_lines.insert(bin.op.span().start().line);
+            }
+
+            Expr::MethodCall(mc)
+                if matches!(
+                    mc.method.to_string().as_str(),
+                    "checked_mul" | "saturating_mul" | "wrapping_mul"
+                ) && is_division(&mc.receiver) =>
+            {
+                self.div_mul_lines.insert(mc.method.span().start().line);
+            }
+
+            _ => {}
+        }
+    }
+}
+
+impl PrecisionScan<'_> {
+    /// Best-effort integer type of `expr`.
+    fn int_type(&self, expr: &Expr, depth: usize) -> Option<String> {
+        if depth > 8 {
+            return None;
+        }
+        let inner = |e: &Expr| self.int_type(e, depth + 1);
+        match expr {
+            Expr::Cast(c) => type_name(&c.ty).filter(|t| int_bits(t).is_some()),
+            Expr::Lit(l) => match &l.lit {
+                Lit::Int(i) if !i.suffix().is_empty() => Some(i.suffix().to_string()),
+                _ => None,
+            },
+            Expr::Path(p) => {
+                let name = p.path.get_ident()?.to_string();
+                self.types
+                    .get(&name)
+                    .cloned()
+                    .or_else(|| self.locals.get(&name).and_then(inner))
+            }
+            Expr::Binary(b) => inner(&b.left).or_else(|| inner(&b.right)),
+            Expr::MethodCall(mc) => {
+                let m = mc.method.to_string();
+                let keeps_type = m.starts_with("checked_")
+                    || m.starts_with("saturating_")
+                    || m.starts_with("wrapping_")
+                    || matches!(
+                        m.as_str(),
+                        "unwrap" | "expect" | "pow" | "min" | "max" | "ok_or" | "ok_or_else"
+                    );
+                if keeps_type {
+                    inner(&mc.receiver)
+                } else {
+                    None
+                }
+            }
+            Expr::Paren(p) => inner(&p.expr),
+            Expr::Group(g) => inner(&g.expr),
+            Expr::Unary(u) => inner(&u.expr),
+            Expr::Try(t) => inner(&t.expr),
+            _ => None,
+        }
+    }
+}
+
+/// Types of instruction arguments and of `let x: T = ..` bindings.
+fn declared_types(func: &ItemFn) -> HashMap<String, String> {
+    struct Annotated(HashMap<String, String>);
+    impl ExprVisitor for Annotated {
+        fn visit_local(&mut self, local: &Local) {
+            if let Pat::Type(pt) = &local.pat
+                && let Pat::Ident(pi) = &*pt.pat
+                && let Some(ty) = type_name(&pt.ty)
+            {
+                self.0.insert(pi.ident.to_string(), ty);
+            }
+        }
+    }
+
+    let mut types = Annotated(HashMap::new());
+    for arg in &func.sig.inputs {
+        if let FnArg::Typed(pt) = arg
+            && let Pat::Ident(pi) = &*pt.pat
+            && let Some(ty) = type_name(&pt.ty)
+        {
+            types.0.insert(pi.ident.to_string(), ty);
+        }
+    }
+    walk_fn(func, &mut types);
+    types.0
+}
+
+fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(tp) => tp.path.get_ident().map(|i| i.to_string()),
+        _ => None,
+    }
+}
+
+fn is_float_type(ty: &Type) -> bool {
+    matches!(type_name(ty).as_deref(), Some("f32" | "f64"))
+}
+
+/// Bit width and signedness of a primitive integer type.
+fn int_bits(ty: &str) -> Option<(u32, bool)> {
+    let signed = ty.starts_with('i');
+    let bits = match ty.strip_prefix(['u', 'i'])? {
+        "8" => 8,
+        "16" => 16,
+        "32" => 32,
+        "64" | "size" => 64,
+        "128" => 128,
+        _ => return None,
+    };
+    Some((bits, signed))
+}
+
+/// Whether converting `from` to `to` with `as` can lose information.
+fn is_lossy(from: &str, to: &str) -> bool {
+    let (Some((from_bits, from_signed)), Some((to_bits, to_signed))) =
+        (int_bits(from), int_bits(to))
+    else {
+        return false;
+    };
+    match (from_signed, to_signed) {
+        (false, false) | (true, true) => to_bits < from_bits,
+        (false, true) => to_bits <= from_bits,
+        (true, false) => true,
+    }
+}
+
+/// Whether `expr` is (a wrapper around) a division.
+fn is_division(expr: &Expr) -> bool {
+    match expr {
+        Expr::Binary(b) => matches!(b.op, BinOp::Div(_)),
+        Expr::Paren(p) => is_division(&p.expr),
+        Expr::Group(g) => is_division(&g.expr),
+        Expr::Cast(c) => is_division(&c.expr),
+        Expr::Try(t) => is_division(&t.expr),
+        Expr::MethodCall(mc) => match mc.method.to_string().as_str() {
+            "checked_div" | "saturating_div" | "wrapping_div" | "div" => true,
+            "unwrap" | "expect" | "ok_or" | "ok_or_else" | "unwrap_or" => is_division(&mc.receiver),
+            _ => false,
+        },
+        _ => false,
+    }
+}
@@ -1,5 +1,131 @@
+use crate::utils::{
+    account_constraints, account_type, find_constraint, find_field, instruction_attr_args,
+    mentioned_ident,
+};
+use crate::visit::walk_expr;
 use colored::*;
-use syn::{Expr, ExprCall, ExprField, ExprMethodCall, ExprPath, ItemFn, Member, Stmt};
+use std::collections::HashSet;
+use syn::{
+    BinOp, Expr, ExprCall, ExprField, ExprLit, ExprMethodCall, ExprPath, Fields, ItemFn,
+    ItemStruct, Lit, Member, Stmt, spanned::Spanned,
+};
+
+/// Validate `#[account(realloc = ..., realloc::payer = ..., realloc::zero = ...)]`.
+pub fn check_realloc_constraints(item_struct: &ItemStruct, file: &str) {
+    let Fields::Named(fields) = &item_struct.fields else {
+        return;
+    };
+    let struct_name = &item_struct.ident;
+    let ix_args = instruction_attr_args(item_struct);
+    let has_system_program = fields.named.iter().any(|f| {
+        f.ident.as_ref().is_some_and(|i| i == "system_program")
+            || account_type(&f.ty).is_some_and(|(wrapper, inner)| {
+                wrapper == "Program" && inner.as_deref() == Some("System")
+            })
+    });
+    // instruction arguments some `constraint = ...` bounds from above
+    let bounded: HashSet<String> = fields
+        .named
+        .iter()
+        .flat_map(account_constraints)
+        .filter(|c| c.key == "constraint")
+        .filter_map(|c| c.value)
+        .flat_map(|v| upper_bounded(&v, &ix_args))
+        .collect();
+
+    for field in &fields.named {
+        let constraints = account_constraints(field);
+        let Some(size) = find_constraint(&constraints, "realloc").and_then(|c| c.value.as_ref())
+        else {
+            continue;
+        };
+        let name = field.ident.as_ref().unwrap();
+        let line = size.span().start().line;
+        let report = |level: ColoredString, problem: String| {
+            println!(
+                "{} `realloc` on `{}` in struct `{}` {}. ({}:{})\n",
+                level, name, struct_name, problem, file, line
+            );
+        };
+
+        match find_constraint(&constraints, "realloc::payer").and_then(|c| c.value.as_ref()) {
+            None => report(
+                "[ERROR]".red().bold(),
+                "has no `realloc::payer`, so nobody funds the extra rent".to_string(),
+            ),
+            Some(Expr::Path(p)) if p.path.get_ident().is_some() => {
+                let payer = p.path.get_ident().unwrap().to_string();
+                if let Some(payer_field) = find_field(item_struct, &payer) {
+                    let is_signer = account_type(&payer_field.ty)
+                        .is_some_and(|(wrapper, _)| wrapper == "Signer");
+                    let is_mut =
+                        find_constraint(&account_constraints(payer_field), "mut").is_some();
+                    if !is_signer || !is_mut {
+                        report(
+                            "[ERROR]".red().bold(),
+                            format!(
+                                "is paid by `{}`, which must be a `Signer` marked `mut` to transfer rent",
+                                payer
+                            ),
+                        );
+                    }
+                }
+            }
+            Some(_) => {}
+        }
+
+        if !has_system_program {
+            report(
+                "[ERROR]".red().bold(),
+                "needs `system_program: Program<'info, System>` in the struct to move lamports"
+                    .to_string(),
+            );
+        }
+
+        let variable_size = !matches!(size, Expr::Lit(_));
+        if variable_size
+            && matches!(
+                find_constraint(&constraints, "realloc::zero").and_then(|c| c.value.as_ref()),
+                Some(Expr::Lit(ExprLit { lit: Lit::Bool(b), .. })) if !b.value
+            )
+        {
+            report(
+                "[WARNING]".yellow().bold(),
+                "uses `realloc::zero = false` with a variable size; shrinking and growing again within a transaction exposes stale bytes. \
+Use `realloc::zero = true` when the account can shrink"
+                    .to_string(),
+            );
+        }
+
+        if let Some(arg) = mentioned_ident(size, &ix_args)
+            && !bounded.contains(&arg)
+        {
+            report(
+                "[WARNING]".yellow().bold(),
+                format!(
+                    "computes its size from instruction argument `{}` without an upper bound; callers can grow it up to the 10 KiB per-instruction limit at the payer's expense",
+                    arg
+                ),
+            );
+        }
+    }
+}
+
+/// Instruction arguments compared with `<`/`<=` (or `>`/`>=` from the other side).
+fn upper_bounded(expr: &Expr, ix_args: &HashSet<String>) -> Vec<String> {
+    let mut out = Vec::new();
+    walk_expr(expr, &mut |e: &Expr| {
+        if let Expr::Binary(bin) = e {
+            let small = match bin.op {
+                BinOp::Lt(_) | BinOp::Le(_) => &bin.left,
+                BinOp::Gt(_) | BinOp::Ge(_) => &bin.right,
+                _ => return,
+            };
+            out.extend(mentioned_ident(small, ix_args));
+        }
+    });
+    out
+}
 
 pub fn check_realloc_usage(func: &ItemFn, file: &str) {
     for stmt in &func.block.stmts {
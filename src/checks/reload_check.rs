@@ -0,0 +1,142 @@
+use crate::checks::cpi_check::CpiResolver;
+use crate::utils::{
+    AccountsStructMap, account_ref, account_type, accounts_in, collect_locals, context_struct_name,
+};
+use crate::visit::{ExprVisitor, walk_fn};
+use colored::*;
+use proc_macro2::LineColumn;
+use std::collections::{HashMap, HashSet};
+use syn::{Expr, Fields, ItemFn, ItemStruct, Member, spanned::Spanned};
+
+enum Event {
+    Cpi {
+        name: String,
+        accounts: HashSet<String>,
+    },
+    Reload(String),
+    Read(String),
+}
+
+/// Collects CPIs, reloads and account reads of a handler together with their position.
+struct ReloadScan<'a> {
+    cpi: &'a CpiResolver<'a>,
+    deserialized: &'a HashSet<String>,
+    locals: HashMap<String, Expr>,
+    events: Vec<(LineColumn, Event)>,
+}
+
+/// Warn when an `Account<T>` passed into a CPI is read later in the same
+/// handler without an intervening `.reload()`.
+pub fn check_reload_after_cpi(
+    func: &ItemFn,
+    file: &str,
+    accounts_structs: &AccountsStructMap,
+    cpi: &CpiResolver,
+) {
+    let Some(item_struct) =
+        context_struct_name(func).and_then(|n| accounts_structs.get(&n).copied())
+    else {
+        return;
+    };
+    let deserialized = deserialized_accounts(item_struct);
+    if deserialized.is_empty() {
+        return;
+    }
+
+    let mut scan = ReloadScan {
+        cpi,
+        deserialized: &deserialized,
+        locals: collect_locals(func),
+        events: Vec::new(),
+    };
+    walk_fn(func, &mut scan);
+    // a CPI is positioned at the end of its call, so reads inside its
+    // arguments sort before it
+    scan.events.sort_by_key(|(pos, _)| (pos.line, pos.column));
+
+    let fn_name = func.sig.ident.to_string();
+    let mut stale: HashMap<String, (String, usize)> = HashMap::new();
+    for (pos, event) in scan.events {
+        match event {
+            Event::Cpi { name, accounts } => {
+                for account in accounts {
+                    stale.insert(account, (name.clone(), pos.line));
+                }
+            }
+            Event::Reload(account) => {
+                stale.remove(&account);
+            }
+            Event::Read(account) => {
+                if let Some((cpi, cpi_line)) = stale.remove(&account) {
+                    println!(
+                        "{} `{}` is read in `{}` after being passed to CPI `{}` (line {}) without `.reload()?`. \
+Its deserialized data may be stale. ({}:{})\n",
+                        "[WARNING]".yellow().bold(),
+                        account,
+                        fn_name,
+                        cpi,
+                        cpi_line,
+                        file,
+                        pos.line
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Fields holding deserialized account data (`Account<T>`, `InterfaceAccount<T>`),
+/// the only ones that go stale across a CPI.
+fn deserialized_accounts(item_struct: &ItemStruct) -> HashSet<String> {
+    let mut out = HashSet::new();
+    if let Fields::Named(fields) = &item_struct.fields {
+        for field in &fields.named {
+            if let Some((wrapper, _)) = account_type(&field.ty)
+                && (wrapper == "Account" || wrapper == "InterfaceAccount")
+            {
+                out.insert(field.ident.as_ref().unwrap().to_string());
+            }
+        }
+    }
+    out
+}
+
+impl ReloadScan<'_> {
+    fn account_of(&self, expr: &Expr) -> Option<String> {
+        account_ref(expr, &self.locals).filter(|name| self.deserialized.contains(name))
+    }
+}
+
+impl ExprVisitor for ReloadScan<'_> {
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Call(call) => {
+                if let Some(name) = self.cpi.cpi_name(call, &self.locals) {
+                    let accounts = call
+                        .args
+                        .iter()
+                        .flat_map(|a| accounts_in(a, &self.locals))
+                        .filter(|a| self.deserialized.contains(a))
+                        .collect();
+                    self.events
+                        .push((call.span().end(), Event::Cpi { name, accounts }));
+                }
+            }
+
+            Expr::MethodCall(mc) if mc.method == "reload" => {
+                if let Some(account) = self.account_of(&mc.receiver) {
+                    self.events.push((mc.span().end(), Event::Reload(account)));
+                }
+            }
+
+            // `ctx.accounts.vault.amount` or `vault.amount`
+            Expr::Field(f) if matches!(f.member, Member::Named(_)) => {
+                if let Some(account) = self.account_of(&f.base) {
+                    self.events.push((f.span().start(), Event::Read(account)));
+                }
+            }
+
+            _ => {}
+        }
+    }
+}
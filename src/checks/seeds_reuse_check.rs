@@ -1,8 +1,17 @@
+use crate::utils::{
+    account_constraints, constraint_seeds, find_constraint, instruction_attr_types, mentioned_ident,
+};
 use colored::*;
-use std::collections::HashMap;
-use syn::{Fields, ItemStruct, Meta, spanned::Spanned};
+use quote::ToTokens;
+use std::collections::{HashMap, HashSet};
+use syn::{Fields, ItemStruct, Type, spanned::Spanned};
 // use syn::Error;
 
+/// Seeds per PDA allowed by the runtime, bump included.
+const MAX_SEEDS: usize = 16;
+/// Longest single seed the runtime accepts, in bytes.
+const MAX_SEED_LEN: usize = 32;
+
 pub struct GlobalSeedUsage {
     struct_name: String,
     field_name: String,
@@ -14,35 +23,124 @@ pub struct GlobalSeedUsage {
 pub fn collect_seeds(item_struct: &ItemStruct, filename: &str, out: &mut Vec<GlobalSeedUsage>) {
     if let Fields::Named(fields) = &item_struct.fields {
         for field in &fields.named {
-            for attr in &field.attrs {
-                if !attr.path().is_ident("account") {
-                    continue;
-                }
-                if let Meta::List(meta_list) = &attr.meta {
-                    let raw = meta_list.tokens.to_string();
-                    if let Some(pos) = raw.find("seeds") {
-                        if let Some(bs) = raw[pos..].find('[') {
-                            let start = pos + bs;
-                            if let Some(re) = raw[start + 1..].find(']') {
-                                let end = start + 1 + re;
-                                let inner = &raw[start + 1..end]; // `b"pool", signer.key().as_ref()`
-                                let first =
-                                    inner.splitn(2, ',').next().unwrap_or("").trim().to_string();
-
-                                let line = attr.span().start().line;
-                                out.push(GlobalSeedUsage {
-                                    struct_name: item_struct.ident.to_string(),
-                                    field_name: field.ident.as_ref().unwrap().to_string(),
-                                    prefix: first.clone(),
-                                    file: filename.to_string(),
-                                    line,
-                                });
-                            }
-                        }
-                    }
-                }
+            let Some(seeds) = constraint_seeds(&account_constraints(field)) else {
+                continue;
+            };
+            let Some(first) = seeds.first() else {
+                continue;
+            };
+            out.push(GlobalSeedUsage {
+                struct_name: item_struct.ident.to_string(),
+                field_name: field.ident.as_ref().unwrap().to_string(),
+                prefix: first.to_token_stream().to_string(),
+                file: filename.to_string(),
+                line: first.span().start().line,
+            });
+        }
+    }
+}
+
+/// Flag PDA fields whose `seeds = [...]` has no `bump`, too many components,
+/// string arguments that can exceed the seed length limit, or adjacent
+/// variable-length seeds that let different inputs derive the same address.
+pub fn check_seed_constraints(item_struct: &ItemStruct, file: &str) {
+    let Fields::Named(fields) = &item_struct.fields else {
+        return;
+    };
+    let variable: HashSet<String> = instruction_attr_types(item_struct)
+        .into_iter()
+        .filter(|(_, ty)| is_variable_length(ty))
+        .map(|(name, _)| name)
+        .collect();
+
+    for field in &fields.named {
+        let constraints = account_constraints(field);
+        let Some(seeds) = constraint_seeds(&constraints) else {
+            continue;
+        };
+        let name = field.ident.as_ref().unwrap();
+        let line = find_constraint(&constraints, "seeds")
+            .and_then(|c| c.value.as_ref())
+            .map_or_else(|| name.span().start().line, |v| v.span().start().line);
+        let has_bump = find_constraint(&constraints, "bump").is_some();
+
+        if !has_bump {
+            println!(
+                "{} `{}` in struct `{}` has `seeds` but no `bump`, so the PDA is not verified against its canonical bump. \
+Add `bump` or `bump = <stored bump>`. ({}:{})\n",
+                "[WARNING]".yellow().bold(),
+                name,
+                item_struct.ident,
+                file,
+                line
+            );
+        }
+
+        let count = seeds.len() + usize::from(has_bump);
+        if count > MAX_SEEDS {
+            println!(
+                "{} `{}` in struct `{}` derives its PDA from {} seeds (bump included); the runtime allows at most {}, \
+so the address can never be derived. ({}:{})\n",
+                "[ERROR]".red().bold(),
+                name,
+                item_struct.ident,
+                count,
+                MAX_SEEDS,
+                file,
+                line
+            );
+        }
+
+        let args: Vec<Option<String>> = seeds
+            .iter()
+            .map(|seed| mentioned_ident(seed, &variable))
+            .collect();
+        for (seed, arg) in seeds.iter().zip(&args) {
+            if let Some(arg) = arg {
+                println!(
+                    "{} Seed of `{}` in struct `{}` uses variable-length instruction argument `{}`; \
+inputs longer than {} bytes make PDA derivation fail. Bound its length or hash it first. ({}:{})\n",
+                    "[WARNING]".yellow().bold(),
+                    name,
+                    item_struct.ident,
+                    arg,
+                    MAX_SEED_LEN,
+                    file,
+                    seed.span().start().line
+                );
             }
         }
+        for (i, pair) in args.windows(2).enumerate() {
+            if let [Some(a), Some(b)] = pair {
+                println!(
+                    "{} Seeds of `{}` in struct `{}` place variable-length arguments `{}` and `{}` side by side; \
+different inputs (\"ab\" + \"c\" vs \"a\" + \"bc\") derive the same PDA. Separate them with a fixed seed or a length prefix. ({}:{})\n",
+                    "[ERROR]".red().bold(),
+                    name,
+                    item_struct.ident,
+                    a,
+                    b,
+                    file,
+                    seeds[i + 1].span().start().line
+                );
+            }
+        }
+    }
+}
+
+/// `String`, `Vec<u8>`, `&str` and `&[u8]`.
+fn is_variable_length(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(r) => match &*r.elem {
+            Type::Slice(_) => true,
+            elem => is_variable_length(elem) || elem.to_token_stream().to_string() == "str",
+        },
+        Type::Path(tp) => tp
+            .path
+            .segments
+            .last()
+            .is_some_and(|s| s.ident == "String" || s.ident == "Vec"),
+        _ => false,
     }
 }
 
@@ -0,0 +1,444 @@
+use crate::checks::cpi_check::{cpi_account_field, cpi_context_parts, signing_roles};
+use crate::utils::{
+    AccountsStructMap, account_constraints, account_info_ref, collect_locals, constraint_seeds,
+    context_struct_name, instruction_args, strip_ref,
+};
+use crate::visit::walk_fn;
+use colored::*;
+use quote::ToTokens;
+use std::collections::{HashMap, HashSet};
+use syn::{Expr, ExprCall, ExprPath, Fields, ItemFn, ItemStruct, Member, spanned::Spanned};
+
+/// A PDA declared in the Accounts struct with `seeds = [...]`.
+struct Pda {
+    field: String,
+    seeds: Vec<String>,
+}
+
+/// Signer seeds handed to `invoke_signed`, `CpiContext::new_with_signer` or `.with_signer`.
+struct SignerSite {
+    seeds: Expr,
+    /// the `CpiContext` being signed, used to tell which PDA signs; `None` for
+    /// `invoke_signed`
+    context: Option<Expr>,
+    line: usize,
+}
+
+/// Compare the signer seeds used for CPIs with the `seeds`/`bump` declared on
+/// the PDA they sign for.
+pub fn check_signer_seeds(func: &ItemFn, file: &str, accounts_structs: &AccountsStructMap) {
+    let Some(ctx_struct) = context_struct_name(func) else {
+        return;
+    };
+    let Some(item_struct) = accounts_structs.get(&ctx_struct) else {
+        return;
+    };
+
+    let pdas = declared_pdas(item_struct);
+    if pdas.is_empty() {
+        return;
+    }
+
+    let locals = collect_locals(func);
+    let args = instruction_args(func);
+    let fn_name = func.sig.ident.to_string();
+
+    let mut sites = Vec::new();
+    walk_fn(func, &mut |expr: &Expr| {
+        if let Some(site) = signer_site(expr) {
+            sites.push(site);
+        }
+    });
+
+    for site in &sites {
+        let signing = signing_accounts(site, item_struct, &locals);
+
+        for slice in seed_slices(&site.seeds, &locals) {
+            let (seeds, bump) = split_bump(&slice);
+            let seeds: Vec<String> = seeds
+                .iter()
+                .map(|s| normalize_seed(s, &locals, 0))
+                .collect();
+            let Some(pda) = matching_pda(&pdas, &seeds, &signing) else {
+                continue;
+            };
+
+            report_seed_mismatch(pda, &seeds, &fn_name, &ctx_struct, file, site.line);
+
+            match bump {
+                Some(bump) => {
+                    if let Some(arg) = bump_from_args(bump, &locals, &args) {
+                        println!(
+                            "{} Bump in signer seeds for `{}` in `{}` comes from instruction argument `{}`. \
+Use `ctx.bumps.{}` or the canonical bump stored in the account. ({}:{})\n",
+                            "[ERROR]".red().bold(),
+                            pda.field,
+                            fn_name,
+                            arg,
+                            pda.field,
+                            file,
+                            site.line
+                        );
+                    }
+                }
+                // fewer than two seeds is already reported by `cpi_check`
+                None if slice.len() >= 2 => {
+                    println!(
+                        "{} Signer seeds for `{}` in `{}` do not end with the bump, so they cannot sign for the PDA declared in `{}`. ({}:{})\n",
+                        "[ERROR]".red().bold(),
+                        pda.field,
+                        fn_name,
+                        ctx_struct,
+                        file,
+                        site.line
+                    );
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+/// Flag PDAs that sign CPIs with seeds made only of constants: a single
+/// authority then signs for every user and pool ("PDA sharing").
+pub fn check_pda_sharing(func: &ItemFn, file: &str, accounts_structs: &AccountsStructMap) {
+    let item_struct = context_struct_name(func).and_then(|n| accounts_structs.get(&n).copied());
+    let pdas = item_struct.map(declared_pdas).unwrap_or_default();
+    let locals = collect_locals(func);
+    let fn_name = func.sig.ident.to_string();
+
+    let mut sites = Vec::new();
+    walk_fn(func, &mut |expr: &Expr| {
+        if let Some(site) = signer_site(expr) {
+            sites.push(site);
+        }
+    });
+
+    for site in &sites {
+        let signing = item_struct
+            .map(|s| signing_accounts(site, s, &locals))
+            .unwrap_or_default();
+
+        for slice in seed_slices(&site.seeds, &locals) {
+            let seeds = split_bump(&slice).0;
+            if seeds.is_empty() || !seeds.iter().all(|s| is_constant_seed(s, &locals, 0)) {
+                continue;
+            }
+            let seeds: Vec<String> = seeds
+                .iter()
+                .map(|s| normalize_seed(s, &locals, 0))
+                .collect();
+            let authority = match matching_pda(&pdas, &seeds, &signing) {
+                Some(pda) => format!("PDA `{}`", pda.field),
+                None => "PDA".to_string(),
+            };
+            println!(
+                "{} {} signs a CPI in `{}` with seeds [{}] made only of constants, so one authority signs for every user and pool. \
+Include the user or pool key in the seeds. ({}:{})\n",
+                "[WARNING]".yellow().bold(),
+                authority,
+                fn_name,
+                seeds.join(", "),
+                file,
+                site.line
+            );
+        }
+    }
+}
+
+/// PDAs declared with `seeds = [...]` on the fields of an Accounts struct.
+fn declared_pdas(item_struct: &ItemStruct) -> Vec<Pda> {
+    let mut pdas = Vec::new();
+    if let Fields::Named(fields) = &item_struct.fields {
+        for field in &fields.named {
+            if let Some(seeds) = constraint_seeds(&account_constraints(field)) {
+                let no_locals = HashMap::new();
+                pdas.push(Pda {
+                    field: field.ident.as_ref().unwrap().to_string(),
+                    seeds: seeds
+                        .iter()
+                        .map(|s| normalize_seed(s, &no_locals, 0))
+                        .collect(),
+                });
+            }
+        }
+    }
+    pdas
+}
+
+/// The field of `item_struct` declared with exactly these `seeds = [...]`
+/// (without the bump), so its canonical bump is available in `ctx.bumps`.
+pub fn pda_with_seeds(
+    item_struct: &ItemStruct,
+    seeds: &[Expr],
+    locals: &HashMap<String, Expr>,
+) -> Option<String> {
+    let seeds: Vec<String> = seeds.iter().map(|s| normalize_seed(s, locals, 0)).collect();
+    declared_pdas(item_struct)
+        .into_iter()
+        .find(|p| p.seeds == seeds)
+        .map(|p| p.field)
+}
+
+/// Byte/string literals, `SCREAMING_CASE` constants and the program id,
+/// following locals.
+pub fn is_constant_seed(expr: &Expr, locals: &HashMap<String, Expr>, depth: usize) -> bool {
+    if depth > 8 {
+        return false;
+    }
+    match strip_ref(expr) {
+        Expr::Lit(_) => true,
+        Expr::Array(arr) => arr
+            .elems
+            .iter()
+            .all(|e| is_constant_seed(e, locals, depth + 1)),
+        Expr::MethodCall(mc) if mc.args.is_empty() && is_view_method(&mc.method.to_string()) => {
+            is_constant_seed(&mc.receiver, locals, depth + 1)
+        }
+        // `crate::id()`, `ID.to_bytes()`
+        Expr::Call(c) => {
+            c.args.is_empty()
+                && matches!(&*c.func, Expr::Path(p) if p.path.segments.last().unwrap().ident == "id")
+        }
+        Expr::MethodCall(mc) if mc.args.is_empty() && mc.method == "to_bytes" => {
+            is_constant_seed(&mc.receiver, locals, depth + 1)
+        }
+        Expr::Path(p) => {
+            let last = p.path.segments.last().unwrap().ident.to_string();
+            match p.path.get_ident().and_then(|i| locals.get(&i.to_string())) {
+                Some(init) => is_constant_seed(init, locals, depth + 1),
+                None => last
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_'),
+            }
+        }
+        _ => false,
+    }
+}
+
+fn signer_site(expr: &Expr) -> Option<SignerSite> {
+    match expr {
+        Expr::Call(ExprCall { func, args, .. }) => {
+            let Expr::Path(ExprPath { path, .. }) = &**func else {
+                return None;
+            };
+            let mut segs = path.segments.iter().rev();
+            let name = segs.next()?.ident.to_string();
+            let is_signed = name == "invoke_signed"
+                || (name == "new_with_signer"
+                    && segs.next().is_some_and(|s| s.ident == "CpiContext"));
+            if !is_signed {
+                return None;
+            }
+            Some(SignerSite {
+                seeds: args.iter().nth(2)?.clone(),
+                context: (name == "new_with_signer").then(|| expr.clone()),
+                line: expr.span().start().line,
+            })
+        }
+        Expr::MethodCall(mc) if mc.method == "with_signer" => Some(SignerSite {
+            seeds: mc.args.first()?.clone(),
+            context: Some((*mc.receiver).clone()),
+            line: mc.method.span().start().line,
+        }),
+        _ => None,
+    }
+}
+
+/// Unpacks `&[&[b"vault", key.as_ref(), &[bump]]]` (possibly spread over
+/// locals) into its seed slices.
+fn seed_slices(expr: &Expr, locals: &HashMap<String, Expr>) -> Vec<Vec<Expr>> {
+    match resolve_array(expr, locals, 0) {
+        Some(outer) => outer
+            .iter()
+            .filter_map(|slice| resolve_array(slice, locals, 0))
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+pub fn resolve_array(
+    expr: &Expr,
+    locals: &HashMap<String, Expr>,
+    depth: usize,
+) -> Option<Vec<Expr>> {
+    if depth > 8 {
+        return None;
+    }
+    match strip_ref(expr) {
+        Expr::Array(arr) => Some(arr.elems.iter().cloned().collect()),
+        // `seeds[..]`, `seeds.as_ref()`, `seeds.as_slice()`
+        Expr::Index(idx) => resolve_array(&idx.expr, locals, depth + 1),
+        Expr::MethodCall(mc) if mc.args.is_empty() && is_view_method(&mc.method.to_string()) => {
+            resolve_array(&mc.receiver, locals, depth + 1)
+        }
+        Expr::Path(p) => {
+            let init = locals.get(&p.path.get_ident()?.to_string())?;
+            resolve_array(init, locals, depth + 1)
+        }
+        _ => None,
+    }
+}
+
+fn is_view_method(name: &str) -> bool {
+    matches!(name, "as_ref" | "as_bytes" | "as_slice")
+}
+
+/// Splits a trailing `&[bump]` off a seed slice.
+pub fn split_bump(slice: &[Expr]) -> (&[Expr], Option<&Expr>) {
+    if let Some((last, rest)) = slice.split_last()
+        && let Expr::Array(arr) = strip_ref(last)
+        && arr.elems.len() == 1
+    {
+        return (rest, arr.elems.first());
+    }
+    (slice, None)
+}
+
+/// Renders a seed so that `ctx.accounts.user.key().as_ref()` in a handler and
+/// `user.key().as_ref()` in a constraint compare equal.
+fn normalize_seed(expr: &Expr, locals: &HashMap<String, Expr>, depth: usize) -> String {
+    match strip_ref(expr) {
+        Expr::MethodCall(mc) if mc.args.is_empty() && is_view_method(&mc.method.to_string()) => {
+            normalize_seed(&mc.receiver, locals, depth)
+        }
+        // `user.key` (the `AccountInfo` field) is the same as `user.key()`
+        Expr::Field(f) if matches!(&f.member, Member::Named(m) if m == "key") => {
+            format!("{}.key()", normalize_seed(&f.base, locals, depth))
+        }
+        Expr::Path(p) if depth < 8 => {
+            match p.path.get_ident().and_then(|i| locals.get(&i.to_string())) {
+                Some(init) => normalize_seed(init, locals, depth + 1),
+                None => render(expr),
+            }
+        }
+        expr => render(expr),
+    }
+}
+
+fn render(expr: &Expr) -> String {
+    let mut s: String = expr
+        .to_token_stream()
+        .to_string()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    while let Some(rest) = s.strip_prefix('&') {
+        s = rest.to_string();
+    }
+    s.replace("ctx.accounts.", "")
+}
+
+/// Accounts passed in a role of the CPI that must sign, e.g. the `authority`
+/// of a token transfer or the `from` of a system transfer.
+fn signing_accounts(
+    site: &SignerSite,
+    item_struct: &ItemStruct,
+    locals: &HashMap<String, Expr>,
+) -> HashSet<String> {
+    let Some(parts) = site
+        .context
+        .as_ref()
+        .and_then(|c| cpi_context_parts(c, locals))
+    else {
+        return HashSet::new();
+    };
+    let Some(accounts) = &parts.accounts else {
+        return HashSet::new();
+    };
+    signing_roles(&parts.program, item_struct, locals)
+        .iter()
+        .filter_map(|role| {
+            cpi_account_field(accounts, role).and_then(|e| account_info_ref(e, locals))
+        })
+        .collect()
+}
+
+/// The PDA these signer seeds are meant for: the one passed in the CPI's
+/// signing role, otherwise one sharing the first seed.
+fn matching_pda<'a>(
+    pdas: &'a [Pda],
+    seeds: &[String],
+    signing: &HashSet<String>,
+) -> Option<&'a Pda> {
+    let first = seeds.first()?;
+    pdas.iter()
+        .find(|p| signing.contains(&p.field))
+        .or_else(|| pdas.iter().find(|p| p.seeds.first() == Some(first)))
+}
+
+fn report_seed_mismatch(
+    pda: &Pda,
+    seeds: &[String],
+    fn_name: &str,
+    ctx_struct: &str,
+    file: &str,
+    line: usize,
+) {
+    let problem = if pda.seeds.first() != seeds.first() {
+        format!(
+            "use prefix `{}` but `{}` is declared with `{}`",
+            seeds.first().map(String::as_str).unwrap_or(""),
+            pda.field,
+            pda.seeds.first().map(String::as_str).unwrap_or("")
+        )
+    } else if pda.seeds.len() != seeds.len() {
+        format!(
+            "have {} component(s) but `{}` declares {}",
+            seeds.len(),
+            pda.field,
+            pda.seeds.len()
+        )
+    } else if let Some((found, expected)) = seeds.iter().zip(&pda.seeds).find(|(a, b)| a != b) {
+        format!(
+            "use `{}` where `{}` declares `{}`",
+            found, pda.field, expected
+        )
+    } else {
+        return;
+    };
+
+    println!(
+        "{} Signer seeds in `{}` {} (seeds = [{}] in `{}`). ({}:{})\n",
+        "[ERROR]".red().bold(),
+        fn_name,
+        problem,
+        pda.seeds.join(", "),
+        ctx_struct,
+        file,
+        line
+    );
+}
+
+/// If the bump resolves to an instruction argument rather than `ctx.bumps` or
+/// account data, return that argument's name.
+pub fn bump_from_args(
+    bump: &Expr,
+    locals: &HashMap<String, Expr>,
+    args: &HashSet<String>,
+) -> Option<String> {
+    let mut expr = strip_ref(bump);
+    for _ in 0..8 {
+        match expr {
+            Expr::Unary(u) => expr = strip_ref(&u.expr),
+            Expr::Path(p) => {
+                let name = p.path.get_ident()?.to_string();
+                if let Some(init) = locals.get(&name) {
+                    expr = strip_ref(init);
+                } else {
+                    return args.contains(&name).then_some(name);
+                }
+            }
+            // `args.bump` on an instruction-data struct
+            Expr::Field(f) if matches!(f.member, Member::Named(_)) => {
+                let Expr::Path(p) = &*f.base else {
+                    return None;
+                };
+                let base = p.path.get_ident()?.to_string();
+                return args.contains(&base).then(|| render(expr));
+            }
+            _ => return None,
+        }
+    }
+    None
+}
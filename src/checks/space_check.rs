@@ -0,0 +1,86 @@
+use crate::index::{AccountIndex, DISCRIMINATOR_LEN, is_zero_copy};
+use crate::utils::{account_constraints, account_type, find_constraint};
+use colored::*;
+use syn::{BinOp, Expr, Fields, ItemStruct, spanned::Spanned};
+
+/// Compare `space = ...` on `init` fields with the Borsh size of the
+/// `#[account]` struct being created.
+pub fn check_account_space(item_struct: &ItemStruct, file: &str, index: &AccountIndex) {
+    let Fields::Named(fields) = &item_struct.fields else {
+        return;
+    };
+
+    for field in &fields.named {
+        let constraints = account_constraints(field);
+        if find_constraint(&constraints, "init").is_none()
+            && find_constraint(&constraints, "init_if_needed").is_none()
+        {
+            continue;
+        }
+        let Some(space) = find_constraint(&constraints, "space").and_then(|c| c.value.as_ref())
+        else {
+            continue;
+        };
+        let Some((wrapper, Some(inner))) = account_type(&field.ty) else {
+            continue;
+        };
+        // zero-copy layouts follow `repr(C)`, not Borsh
+        if wrapper == "AccountLoader" {
+            continue;
+        }
+        let Some(account) = index.account(&inner) else {
+            continue;
+        };
+        if is_zero_copy(&account.attrs) {
+            continue;
+        }
+        let (Some(size), Some(declared)) = (index.type_size(&inner), index.eval(space)) else {
+            continue;
+        };
+        let expected = DISCRIMINATOR_LEN + size;
+        let name = field.ident.as_ref().unwrap();
+        let line = space.span().start().line;
+
+        if declared < expected {
+            println!(
+                "{} `space` of `{}` in struct `{}` is {} bytes but `{}` needs {} ({} discriminator + {} data); \
+initialization or later writes will fail. Use `{} + {}::INIT_SPACE`. ({}:{})\n",
+                "[ERROR]".red().bold(),
+                name,
+                item_struct.ident,
+                declared,
+                inner,
+                expected,
+                DISCRIMINATOR_LEN,
+                size,
+                DISCRIMINATOR_LEN,
+                inner,
+                file,
+                line
+            );
+        } else if declared > expected && !has_added_term(space) {
+            // `8 + Foo::INIT_SPACE + 64` reserves room on purpose
+            println!(
+                "{} `space` of `{}` in struct `{}` is {} bytes but `{}` needs only {}; if the extra {} bytes are not reserved for growth, they lock up rent. ({}:{})\n",
+                "[INFO]".cyan().bold(),
+                name,
+                item_struct.ident,
+                declared,
+                inner,
+                expected,
+                declared - expected,
+                file,
+                line
+            );
+        }
+    }
+}
+
+/// Whether `space` is written as a sum, e.g. `8 + Foo::INIT_SPACE + 64`.
+fn has_added_term(space: &Expr) -> bool {
+    match space {
+        Expr::Paren(p) => has_added_term(&p.expr),
+        Expr::Binary(b) => matches!(b.op, BinOp::Add(_)),
+        _ => false,
+    }
+}
@@ -1,71 +1,179 @@
-use syn::{Fields, ItemStruct, Type, TypePath, PathArguments, GenericArgument};
-use std::collections::HashMap;
+use crate::utils::{
+    AccountConstraint, account_constraints, account_type, constraint_seeds, find_constraint,
+};
+use crate::visit::walk_expr;
 use colored::*;
+use quote::ToTokens;
+use syn::{BinOp, Expr, Fields, ItemStruct, Lit};
 
-/// Recursively unwrap wrapper types like Box<Account<...>> to reach the inner type.
-fn unwrap_type_path(ty: &Type) -> Option<&TypePath> {
-    match ty {
-        Type::Path(type_path) => Some(type_path),
-        Type::Reference(inner) => unwrap_type_path(&inner.elem),
-        Type::Paren(inner) => unwrap_type_path(&inner.elem),
-        Type::Group(inner) => unwrap_type_path(&inner.elem),
-        Type::Slice(inner) => unwrap_type_path(&inner.elem),
-        Type::Array(inner) => unwrap_type_path(&inner.elem),
-        _ => None,
-    }
+/// Wrappers that deserialize program-owned data and write it back on exit.
+const DATA_WRAPPERS: &[&str] = &["Account", "AccountLoader", "InterfaceAccount"];
+
+struct TypedAccount {
+    name: String,
+    wrapper: String,
+    inner: String,
+    mutable: bool,
+    seeds: Option<Vec<Expr>>,
+    line: usize,
 }
 
+/// Report pairs of accounts of the same type where at least one is mutable
+/// and nothing (a key-inequality constraint or distinct PDA seeds) stops a
+/// caller from passing the same account twice.
 pub fn check_duplicate_account_types(item_struct: &ItemStruct, file: &str) {
-    let mut type_lines: HashMap<String, Vec<usize>> = HashMap::new();
-
-    if let Fields::Named(fields) = &item_struct.fields {
-        for field in &fields.named {
-            // Walk through wrapper types like Box<Account<T>>
-            let mut current_type = &field.ty;
+    let Fields::Named(fields) = &item_struct.fields else {
+        return;
+    };
 
-            loop {
-                if let Some(tp) = unwrap_type_path(current_type) {
-                    let seg = tp.path.segments.last().unwrap();
-                    if seg.ident == "Account" {
-                        // Extract generic parameter from Account<T>
-                        if let PathArguments::AngleBracketed(args) = &seg.arguments {
-                            for arg in &args.args {
-                                if let GenericArgument::Type(Type::Path(inner_path)) = arg {
-                                    if let Some(inner_seg) = inner_path.path.segments.last() {
-                                        let inner_ty = inner_seg.ident.to_string();
-                                        let line = field.ident.as_ref().map(|i| i.span().start().line).unwrap_or(0);
-                                        type_lines.entry(inner_ty).or_default().push(line);
-                                    }
-                                }
-                            }
-                        }
-                        break;
-                    } else if seg.ident == "Box" {
-                        // Recurse into Box<Account<T>>
-                        if let PathArguments::AngleBracketed(args) = &seg.arguments {
-                            if let Some(GenericArgument::Type(inner_ty)) = args.args.first() {
-                                current_type = inner_ty;
-                                continue;
-                            }
-                        }
-                    }
-                }
+    let mut accounts = Vec::new();
+    let mut constraints = Vec::new();
+    for field in &fields.named {
+        let cs = account_constraints(field);
+        if let Some((wrapper, Some(inner))) = account_type(&field.ty)
+            && DATA_WRAPPERS.contains(&wrapper.as_str())
+            // freshly created accounts cannot alias an existing one
+            && find_constraint(&cs, "init").is_none()
+            && find_constraint(&cs, "zero").is_none()
+        {
+            let name = field.ident.as_ref().unwrap();
+            accounts.push(TypedAccount {
+                name: name.to_string(),
+                wrapper,
+                inner,
+                mutable: find_constraint(&cs, "mut").is_some()
+                    || find_constraint(&cs, "init_if_needed").is_some(),
+                seeds: constraint_seeds(&cs),
+                line: name.span().start().line,
+            });
+        }
+        constraints.extend(cs);
+    }
 
-                break;
+    for (i, a) in accounts.iter().enumerate() {
+        for b in &accounts[i + 1..] {
+            if a.inner != b.inner
+                || !(a.mutable || b.mutable)
+                || keys_differ(&constraints, &a.name, &b.name)
+                || seeds_differ(a.seeds.as_deref(), b.seeds.as_deref())
+            {
+                continue;
             }
+            println!(
+                "{} `{}` and `{}` in struct `{}` are both `{}<{}>` and at least one is mutable, but nothing stops the same account being passed twice. \
+Add `constraint = {}.key() != {}.key()` or derive them from distinct seeds. ({}:{}, {}:{})\nFor more details, see: https://hackmd.io/@S3v3ru5/Byia-fQHJe\n",
+                "[ERROR]".red().bold(),
+                a.name,
+                b.name,
+                item_struct.ident,
+                a.wrapper,
+                a.inner,
+                a.name,
+                b.name,
+                file,
+                a.line,
+                file,
+                b.line
+            );
         }
+    }
+}
 
-        for (ty, lines) in type_lines {
-            if lines.len() > 1 {
-                let line_str = lines.iter().map(|l| format!("{}:{}", file, l)).collect::<Vec<_>>().join(", ");
-                println!(
-                    "{} Duplicate `Account<{}>` fields in struct `{}`. Consider using a separate `#[derive(Accounts)]` struct. ({})\nFor more details, see: https://hackmd.io/@S3v3ru5/Byia-fQHJe\n",
-                    "[ERROR]".red().bold(),
-                    ty,
-                    item_struct.ident,
-                    line_str
-                );
+/// Whether some `constraint = ...` asserts `a.key() != b.key()` (in either order).
+fn keys_differ(constraints: &[AccountConstraint], a: &str, b: &str) -> bool {
+    let mut found = false;
+    for value in constraints
+        .iter()
+        .filter(|c| c.key == "constraint")
+        .filter_map(|c| c.value.as_ref())
+    {
+        walk_expr(value, &mut |e: &Expr| {
+            if let Expr::Binary(bin) = e
+                && matches!(bin.op, BinOp::Ne(_))
+            {
+                let (l, r) = (root_ident(&bin.left), root_ident(&bin.right));
+                if (l.as_deref() == Some(a) && r.as_deref() == Some(b))
+                    || (l.as_deref() == Some(b) && r.as_deref() == Some(a))
+                {
+                    found = true;
+                }
             }
+        });
+    }
+    found
+}
+
+/// `vault` for `vault.key()`, `vault.to_account_info().key`, `&vault.key()`.
+fn root_ident(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Path(p) => p.path.get_ident().map(|i| i.to_string()),
+        Expr::MethodCall(mc) => root_ident(&mc.receiver),
+        Expr::Field(f) => root_ident(&f.base),
+        Expr::Reference(r) => root_ident(&r.expr),
+        Expr::Paren(p) => root_ident(&p.expr),
+        _ => None,
+    }
+}
+
+/// Whether two PDA seed lists can never derive the same address. Seeds are
+/// concatenated before hashing, so `[b"ab"]` and `[b"a", b"b"]` collide; the
+/// lists only differ for sure at a position holding two literals that diverge,
+/// when every earlier seed has the same fixed width on both sides.
+fn seeds_differ(a: Option<&[Expr]>, b: Option<&[Expr]>) -> bool {
+    let (Some(a), Some(b)) = (a, b) else {
+        return false;
+    };
+    for (x, y) in a.iter().zip(b) {
+        if let (Some(x), Some(y)) = (literal_seed(x), literal_seed(y))
+            && x.iter().zip(&y).any(|(p, q)| p != q)
+        {
+            return true;
+        }
+        match (seed_width(x), seed_width(y)) {
+            (Some(wx), Some(wy)) if wx == wy => {}
+            _ => return false,
         }
     }
+    false
+}
+
+/// Width of a seed whose length doesn't depend on runtime data.
+#[derive(PartialEq)]
+enum SeedWidth {
+    Bytes(usize),
+    /// `x.key().as_ref()`
+    Key,
+    /// `x.to_le_bytes()`: fixed by the type of `x`, so only equal to itself
+    Int(String),
+}
+
+fn seed_width(expr: &Expr) -> Option<SeedWidth> {
+    if let Some(bytes) = literal_seed(expr) {
+        return Some(SeedWidth::Bytes(bytes.len()));
+    }
+    match expr {
+        Expr::Reference(r) => seed_width(&r.expr),
+        Expr::MethodCall(mc) if mc.method == "key" => Some(SeedWidth::Key),
+        Expr::MethodCall(mc) if mc.method == "to_le_bytes" || mc.method == "to_be_bytes" => {
+            Some(SeedWidth::Int(mc.to_token_stream().to_string()))
+        }
+        Expr::MethodCall(mc) if mc.args.is_empty() && mc.method == "as_ref" => {
+            seed_width(&mc.receiver)
+        }
+        _ => None,
+    }
+}
+
+/// Bytes of a `b"..."` or `"...".as_bytes()` seed.
+fn literal_seed(expr: &Expr) -> Option<Vec<u8>> {
+    match expr {
+        Expr::Lit(l) => match &l.lit {
+            Lit::ByteStr(b) => Some(b.value()),
+            Lit::Str(s) => Some(s.value().into_bytes()),
+            _ => None,
+        },
+        Expr::Reference(r) => literal_seed(&r.expr),
+        Expr::MethodCall(mc) if mc.args.is_empty() => literal_seed(&mc.receiver),
+        _ => None,
+    }
 }
@@ -0,0 +1,118 @@
+use crate::utils::{
+    AccountsStructMap, account_constraints, account_info_ref, account_type, collect_locals,
+    context_struct_name, find_constraint, find_field,
+};
+use crate::visit::walk_fn;
+use colored::*;
+use syn::{Expr, Field, Fields, ItemFn, ItemStruct, spanned::Spanned};
+
+/// Sysvars whose `from_account_info` readers we look for.
+const SYSVAR_TYPES: &[&str] = &[
+    "Clock",
+    "EpochRewards",
+    "EpochSchedule",
+    "Fees",
+    "LastRestartSlot",
+    "RecentBlockhashes",
+    "Rent",
+    "SlotHashes",
+    "SlotHistory",
+    "StakeHistory",
+];
+
+/// Whether `field` is a raw `AccountInfo`/`UncheckedAccount` whose key is not
+/// pinned with an `address = ...` constraint.
+pub fn is_unchecked_account(field: &Field) -> bool {
+    let raw = account_type(&field.ty)
+        .is_some_and(|(wrapper, _)| wrapper == "AccountInfo" || wrapper == "UncheckedAccount");
+    raw && find_constraint(&account_constraints(field), "address").is_none()
+}
+
+/// Whether a field name is that of a sysvar (`clock`, `rent`,
+/// `instructions_sysvar`, `recent_blockhashes`, ...). Only whole names match,
+/// so `rent_receiver` or `clock_authority` are not mistaken for sysvars.
+pub fn looks_like_sysvar(name: &str) -> bool {
+    matches!(
+        name,
+        "clock"
+            | "rent"
+            | "instructions"
+            | "instructions_sysvar"
+            | "ix_sysvar"
+            | "recent_blockhashes"
+            | "slot_hashes"
+            | "slot_history"
+            | "stake_history"
+            | "epoch_schedule"
+    ) || name.ends_with("_sysvar")
+        || name.starts_with("sysvar_")
+}
+
+/// Flag sysvar-named fields that accept any account.
+pub fn check_sysvar_accounts(item_struct: &ItemStruct, file: &str) {
+    let Fields::Named(fields) = &item_struct.fields else {
+        return;
+    };
+    for field in &fields.named {
+        let name = field.ident.as_ref().unwrap();
+        if looks_like_sysvar(&name.to_string()) && is_unchecked_account(field) {
+            println!(
+                "{} `{}` in struct `{}` looks like a sysvar but is an unchecked account without `address = sysvar::...::ID`; \
+a spoofed account can be passed. Use `Sysvar<'info, T>` or the `address` constraint. ({}:{})\n",
+                "[ERROR]".red().bold(),
+                name,
+                item_struct.ident,
+                file,
+                name.span().start().line
+            );
+        }
+    }
+}
+
+/// Flag `Clock::from_account_info(..)` and friends on unchecked accounts.
+pub fn check_sysvar_usage(func: &ItemFn, file: &str, accounts_structs: &AccountsStructMap) {
+    let locals = collect_locals(func);
+    let item_struct = context_struct_name(func).and_then(|n| accounts_structs.get(&n).copied());
+    let fn_name = func.sig.ident.to_string();
+
+    walk_fn(func, &mut |expr: &Expr| {
+        let Expr::Call(call) = expr else {
+            return;
+        };
+        let Expr::Path(p) = &*call.func else {
+            return;
+        };
+        let segs: Vec<String> = p
+            .path
+            .segments
+            .iter()
+            .map(|s| s.ident.to_string())
+            .collect();
+        let [.., sysvar, method] = segs.as_slice() else {
+            return;
+        };
+        if method != "from_account_info" || !SYSVAR_TYPES.contains(&sysvar.as_str()) {
+            return;
+        }
+
+        let account = call.args.first().and_then(|a| account_info_ref(a, &locals));
+        let unchecked = match (&account, item_struct) {
+            (Some(account), Some(s)) => find_field(s, account).is_none_or(is_unchecked_account),
+            _ => true,
+        };
+        if unchecked {
+            println!(
+                "{} `{}::from_account_info` in `{}` reads {} from an unchecked account. \
+Use `{}::get()` or take it as `Sysvar<'info, {}>`. ({}:{})\n",
+                "[WARNING]".yellow().bold(),
+                sysvar,
+                fn_name,
+                sysvar,
+                sysvar,
+                sysvar,
+                file,
+                expr.span().start().line
+            );
+        }
+    });
+}
@@ -0,0 +1,417 @@
+use crate::checks::cpi_check::{
+    CpiContextParts, CpiResolver, cpi_account_field, cpi_context_parts,
+};
+use crate::utils::{
+    AccountsStructMap, account_constraints, account_info_ref, account_ref, account_type,
+    accounts_in, collect_locals, context_struct_name, find_constraint, find_field,
+};
+use crate::visit::{walk_expr, walk_fn};
+use colored::*;
+use std::collections::{HashMap, HashSet};
+use syn::{BinOp, Expr, Fields, ItemFn, ItemStruct, Member, spanned::Spanned};
+
+/// Modules whose CPIs move tokens.
+pub const TOKEN_MODULES: &[&str] = &["token", "token_2022", "token_interface"];
+
+/// Token-2022 helpers that compute the fee withheld from a transfer.
+const FEE_CALLS: &[&str] = &["transfer_fee", "calculate_epoch_fee", "get_epoch_fee"];
+
+/// Flag token accounts moved through `transfer`/`mint_to` CPIs whose mint or
+/// authority is not pinned by the Accounts struct, and mints minted to
+/// without `mint::authority`.
+pub fn check_token_constraints(
+    func: &ItemFn,
+    file: &str,
+    accounts_structs: &AccountsStructMap,
+    cpi: &CpiResolver,
+) {
+    let Some(item_struct) =
+        context_struct_name(func).and_then(|n| accounts_structs.get(&n).copied())
+    else {
+        return;
+    };
+    let locals = collect_locals(func);
+    let fn_name = func.sig.ident.to_string();
+    let mut reported = HashSet::new();
+
+    walk_fn(func, &mut |expr: &Expr| {
+        let Expr::Call(call) = expr else {
+            return;
+        };
+        let Some(name) = cpi.cpi_name(call, &locals) else {
+            return;
+        };
+        let Some((module, function)) = name.rsplit_once("::") else {
+            return;
+        };
+        if !TOKEN_MODULES.contains(&module) {
+            return;
+        }
+        let roles: &[&str] = match function {
+            "transfer" | "transfer_checked" => &["from", "to"],
+            "mint_to" | "mint_to_checked" => &["mint", "to"],
+            _ => return,
+        };
+        let Some(accounts) = call
+            .args
+            .first()
+            .and_then(|a| cpi_context_parts(a, &locals))
+            .and_then(|parts| parts.accounts)
+        else {
+            return;
+        };
+
+        for role in roles {
+            let Some(account) =
+                cpi_account_field(&accounts, role).and_then(|e| account_info_ref(e, &locals))
+            else {
+                continue;
+            };
+            if !reported.insert(account.clone()) {
+                continue;
+            }
+            let Some(missing) = unpinned(item_struct, &account) else {
+                continue;
+            };
+            println!(
+                "{} `{}` is used as `{}` in `{}` within `{}`, but struct `{}` does not constrain its {}. \
+Add {} (or an equivalent `constraint = ...`). ({}:{})\n",
+                "[WARNING]".yellow().bold(),
+                account,
+                role,
+                name,
+                fn_name,
+                item_struct.ident,
+                missing.join(" or "),
+                missing_constraints(&missing),
+                file,
+                call.span().start().line
+            );
+        }
+    });
+}
+
+/// Token-2022 pitfalls in handlers whose accounts accept interface types:
+/// legacy `transfer` instead of `transfer_checked`, crediting the sent amount
+/// although transfer-fee mints deliver less, and transfer-hook mints whose
+/// extra accounts are never forwarded.
+pub fn check_token_2022(
+    func: &ItemFn,
+    file: &str,
+    accounts_structs: &AccountsStructMap,
+    cpi: &CpiResolver,
+) {
+    let Some(item_struct) =
+        context_struct_name(func).and_then(|n| accounts_structs.get(&n).copied())
+    else {
+        return;
+    };
+    if !uses_token_interface(item_struct) {
+        return;
+    }
+    let locals = collect_locals(func);
+    let fn_name = func.sig.ident.to_string();
+
+    let mut transfers = Vec::new();
+    let mut fee_computed = false;
+    let mut credited = Vec::new();
+    // (account, line) of each `reload()`, and accounts read by each subtraction
+    let mut reloads = Vec::new();
+    let mut subtractions = Vec::new();
+    walk_fn(func, &mut |expr: &Expr| match expr {
+        Expr::Call(call) => {
+            if let Some(name) = cpi.cpi_name(call, &locals)
+                && let Some((module, function)) = name.rsplit_once("::")
+                && TOKEN_MODULES.contains(&module)
+                && (function == "transfer" || function == "transfer_checked")
+            {
+                let ctx = call
+                    .args
+                    .first()
+                    .and_then(|a| cpi_context_parts(a, &locals));
+                // a hook can only run for a Token-2022 mint, and its extra
+                // accounts must come through this CPI's remaining accounts
+                let misses_hook_accounts = ctx.as_ref().is_some_and(|c| {
+                    !c.remaining_accounts && hook_capable(item_struct, c, &locals)
+                });
+                let destination = ctx
+                    .as_ref()
+                    .and_then(|c| c.accounts.as_ref())
+                    .and_then(|accounts| cpi_account_field(accounts, "to"))
+                    .and_then(|e| account_info_ref(e, &locals));
+                let amount = call.args.iter().nth(1).and_then(|a| match a {
+                    Expr::Path(p) => p.path.get_ident().map(|i| i.to_string()),
+                    _ => None,
+                });
+                transfers.push((
+                    name.clone(),
+                    function == "transfer",
+                    amount,
+                    destination,
+                    misses_hook_accounts,
+                    call.span().start().line,
+                ));
+            } else if let Expr::Path(p) = &*call.func
+                && p.path
+                    .segments
+                    .last()
+                    .is_some_and(|s| FEE_CALLS.contains(&s.ident.to_string().as_str()))
+            {
+                fee_computed = true;
+            }
+        }
+        Expr::Binary(bin) => match bin.op {
+            BinOp::AddAssign(_) => credited.extend(idents_in(&bin.right)),
+            BinOp::Sub(_) | BinOp::SubAssign(_) => {
+                subtractions.push((accounts_in(expr, &locals), bin.span().start().line));
+            }
+            _ => {}
+        },
+        Expr::MethodCall(mc) => {
+            let method = mc.method.to_string();
+            let line = mc.method.span().start().line;
+            match method.as_str() {
+                "checked_add" | "saturating_add" => {
+                    credited.extend(mc.args.iter().flat_map(idents_in));
+                }
+                "checked_sub" | "saturating_sub" | "wrapping_sub" => {
+                    subtractions.push((accounts_in(expr, &locals), line));
+                }
+                "reload" => {
+                    if let Some(account) = account_ref(&mc.receiver, &locals) {
+                        reloads.push((account, line));
+                    }
+                }
+                _ => fee_computed |= FEE_CALLS.contains(&method.as_str()),
+            }
+        }
+        _ => {}
+    });
+
+    // the amount received is the destination's balance after the transfer,
+    // reloaded, minus its balance before
+    let measures_received = |account: &str| {
+        reloads.iter().any(|(reloaded, reload_line)| {
+            reloaded == account
+                && subtractions
+                    .iter()
+                    .any(|(read, line)| line > reload_line && read.contains(account))
+        })
+    };
+
+    for (name, legacy, amount, destination, misses_hook_accounts, line) in &transfers {
+        if *legacy {
+            println!(
+                "{} `{}` in `{}` uses the legacy transfer although `{}` accepts Token-2022 accounts; it fails for mints with transfer fees or hooks. \
+Use `transfer_checked` with the mint and decimals. ({}:{})\n",
+                "[WARNING]".yellow().bold(),
+                name,
+                fn_name,
+                item_struct.ident,
+                file,
+                line
+            );
+        }
+        let handles_fees = fee_computed || destination.as_deref().is_some_and(&measures_received);
+        if !handles_fees
+            && let Some(amount) = amount
+            && credited.contains(amount)
+        {
+            println!(
+                "{} `{}` in `{}` records `{}` as received, but a Token-2022 mint with a transfer fee delivers less. \
+Credit the post-transfer balance difference or subtract the fee. ({}:{})\n",
+                "[WARNING]".yellow().bold(),
+                name,
+                fn_name,
+                amount,
+                file,
+                line
+            );
+        }
+        if *misses_hook_accounts {
+            println!(
+                "{} `{}` in `{}` forwards no remaining accounts; transfers of Token-2022 mints with a transfer hook fail without the hook's extra accounts. \
+Pass them with `.with_remaining_accounts(..)`. ({}:{})\n",
+                "[INFO]".cyan().bold(),
+                name,
+                fn_name,
+                file,
+                line
+            );
+        }
+    }
+}
+
+/// Whether the transfer's mint can be a Token-2022 mint: taken as an
+/// `InterfaceAccount` and not sent through the legacy `Program<'info, Token>`.
+fn hook_capable(
+    item_struct: &ItemStruct,
+    ctx: &CpiContextParts,
+    locals: &HashMap<String, Expr>,
+) -> bool {
+    let field_type = |expr: &Expr| {
+        account_info_ref(expr, locals)
+            .and_then(|name| find_field(item_struct, &name))
+            .and_then(|field| account_type(&field.ty))
+    };
+    let legacy_program =
+        field_type(&ctx.program).is_some_and(|(_, inner)| inner.as_deref() == Some("Token"));
+    let interface_mint = ctx
+        .accounts
+        .as_ref()
+        .and_then(|accounts| cpi_account_field(accounts, "mint"))
+        .and_then(field_type)
+        .is_some_and(|(wrapper, _)| wrapper == "InterfaceAccount");
+    interface_mint && !legacy_program
+}
+
+/// Flag Accounts structs that accept Token-2022 accounts through
+/// `InterfaceAccount` but only take the legacy `Program<'info, Token>`.
+pub fn check_token_program_mismatch(item_struct: &ItemStruct, file: &str) {
+    let Fields::Named(fields) = &item_struct.fields else {
+        return;
+    };
+    let mut interface_account = None;
+    let mut legacy_program = None;
+    for field in &fields.named {
+        match account_type(&field.ty) {
+            Some((wrapper, Some(inner)))
+                if wrapper == "InterfaceAccount"
+                    && (inner == "Mint" || inner == "TokenAccount") =>
+            {
+                interface_account.get_or_insert(field.ident.as_ref().unwrap());
+            }
+            Some((wrapper, Some(inner))) if wrapper == "Program" && inner == "Token" => {
+                legacy_program.get_or_insert(field.ident.as_ref().unwrap());
+            }
+            _ => {}
+        }
+    }
+    if let (Some(account), Some(program)) = (interface_account, legacy_program) {
+        println!(
+            "{} Struct `{}` accepts Token-2022 accounts through `InterfaceAccount` (`{}`) but `{}` is `Program<'info, Token>`, which cannot operate on them. \
+Use `Interface<'info, TokenInterface>`. ({}:{})\n",
+            "[WARNING]".yellow().bold(),
+            item_struct.ident,
+            account,
+            program,
+            file,
+            program.span().start().line
+        );
+    }
+}
+
+/// Whether a struct takes `Interface<..>` programs or `InterfaceAccount<..>` accounts.
+fn uses_token_interface(item_struct: &ItemStruct) -> bool {
+    let Fields::Named(fields) = &item_struct.fields else {
+        return false;
+    };
+    fields.named.iter().any(|f| {
+        account_type(&f.ty)
+            .is_some_and(|(wrapper, _)| wrapper == "Interface" || wrapper == "InterfaceAccount")
+    })
+}
+
+/// Plain identifiers mentioned in `expr`.
+fn idents_in(expr: &Expr) -> Vec<String> {
+    let mut out = Vec::new();
+    walk_expr(expr, &mut |e: &Expr| {
+        if let Expr::Path(p) = e
+            && let Some(ident) = p.path.get_ident()
+        {
+            out.push(ident.to_string());
+        }
+    });
+    out
+}
+
+/// What is not pinned about a token account (`mint`, `authority`) or mint
+/// (`mint authority`); `None` when fully constrained or not a token type.
+fn unpinned(item_struct: &ItemStruct, account: &str) -> Option<Vec<&'static str>> {
+    let field = find_field(item_struct, account)?;
+    let (_, inner) = account_type(&field.ty)?;
+    let constraints = account_constraints(field);
+    if find_constraint(&constraints, "address").is_some()
+        || find_constraint(&constraints, "seeds").is_some()
+    {
+        return None;
+    }
+    let keys: HashSet<&str> = constraints.iter().map(|c| c.key.as_str()).collect();
+    let has_one: HashSet<String> = constraints
+        .iter()
+        .filter(|c| c.key == "has_one")
+        .filter_map(|c| match &c.value {
+            Some(Expr::Path(p)) => p.path.get_ident().map(|i| i.to_string()),
+            _ => None,
+        })
+        .collect();
+    let read = fields_read_in_constraints(item_struct, account);
+
+    let missing: Vec<&'static str> = match inner.as_deref()? {
+        "TokenAccount" => {
+            let mint = keys.contains("token::mint")
+                || keys.contains("associated_token::mint")
+                || has_one.contains("mint")
+                || read.contains("mint");
+            let authority = keys.contains("token::authority")
+                || keys.contains("associated_token::authority")
+                || has_one.contains("owner")
+                || has_one.contains("authority")
+                || read.contains("owner");
+            [
+                (!mint).then_some("mint"),
+                (!authority).then_some("authority"),
+            ]
+            .into_iter()
+            .flatten()
+            .collect()
+        }
+        "Mint" => {
+            let authority = keys.contains("mint::authority") || read.contains("mint_authority");
+            (!authority)
+                .then_some("mint authority")
+                .into_iter()
+                .collect()
+        }
+        _ => return None,
+    };
+    (!missing.is_empty()).then_some(missing)
+}
+
+fn missing_constraints(missing: &[&str]) -> String {
+    missing
+        .iter()
+        .map(|m| match *m {
+            "mint" => "`token::mint`/`associated_token::mint`",
+            "authority" => "`token::authority`/`associated_token::authority`",
+            _ => "`mint::authority`",
+        })
+        .collect::<Vec<_>>()
+        .join(" and ")
+}
+
+/// Fields of `account` read in any `constraint = ...` of the struct, e.g.
+/// `mint` for `vault.mint == mint.key()`.
+fn fields_read_in_constraints(item_struct: &ItemStruct, account: &str) -> HashSet<String> {
+    let mut read = HashSet::new();
+    let Fields::Named(fields) = &item_struct.fields else {
+        return read;
+    };
+    for value in fields
+        .named
+        .iter()
+        .flat_map(account_constraints)
+        .filter(|c| c.key == "constraint")
+        .filter_map(|c| c.value)
+    {
+        walk_expr(&value, &mut |e: &Expr| {
+            if let Expr::Field(f) = e
+                && let (Expr::Path(base), Member::Named(member)) = (&*f.base, &f.member)
+                && base.path.is_ident(account)
+            {
+                read.insert(member.to_string());
+            }
+        });
+    }
+    read
+}
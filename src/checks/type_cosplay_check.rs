@@ -0,0 +1,233 @@
+use crate::utils::{
+    AccountsStructMap, account_constraints, account_info_ref, accounts_in, collect_locals,
+    context_struct_name, find_constraint, int_lit, reads_account_data,
+};
+use crate::visit::{ExprVisitor, walk_expr, walk_fn};
+use colored::*;
+use std::collections::{HashMap, HashSet};
+use syn::{
+    BinOp, Expr, ExprCall, ExprPath, Fields, ItemFn, Macro, Member, Token, punctuated::Punctuated,
+    spanned::Spanned,
+};
+
+/// A call that turns raw account bytes into a typed value.
+struct Deserialization {
+    name: String,
+    accounts: HashSet<String>,
+    /// whether the call itself verifies the 8-byte discriminator
+    checks_discriminator: bool,
+    /// whether the call itself verifies the owning program
+    checks_owner: bool,
+    line: usize,
+}
+
+/// Flag hand-rolled deserialization of account data that skips the
+/// discriminator or owner check Anchor's `Account<T>` would perform.
+pub fn check_type_cosplay(func: &ItemFn, file: &str, accounts_structs: &AccountsStructMap) {
+    let locals = collect_locals(func);
+    let owner_constrained: HashSet<String> = context_struct_name(func)
+        .and_then(|name| accounts_structs.get(&name).copied())
+        .map(|s| match &s.fields {
+            Fields::Named(fields) => fields
+                .named
+                .iter()
+                .filter(|f| {
+                    let cs = account_constraints(f);
+                    find_constraint(&cs, "owner").is_some()
+                        || find_constraint(&cs, "address").is_some()
+                })
+                .map(|f| f.ident.as_ref().unwrap().to_string())
+                .collect(),
+            _ => HashSet::new(),
+        })
+        .unwrap_or_default();
+
+    let mut checks = Checks {
+        locals: &locals,
+        found: Vec::new(),
+        owner_checked: HashSet::new(),
+        any_owner_checked: false,
+        discriminator_checked: HashSet::new(),
+        any_discriminator_checked: false,
+    };
+    walk_fn(func, &mut checks);
+
+    let fn_name = func.sig.ident.to_string();
+    for d in checks.found {
+        let owner_ok = d.checks_owner
+            || if d.accounts.is_empty() {
+                checks.any_owner_checked
+            } else {
+                d.accounts
+                    .iter()
+                    .all(|a| checks.owner_checked.contains(a) || owner_constrained.contains(a))
+            };
+        let disc_ok = d.checks_discriminator
+            || if d.accounts.is_empty() {
+                checks.any_discriminator_checked
+            } else {
+                d.accounts
+                    .iter()
+                    .all(|a| checks.discriminator_checked.contains(a))
+            };
+
+        let missing = match (disc_ok, owner_ok) {
+            (true, true) => continue,
+            (false, false) => "discriminator or owner",
+            (false, true) => "discriminator",
+            (true, false) => "owner",
+        };
+        let level = if !disc_ok && !owner_ok {
+            "[ERROR]".red().bold()
+        } else {
+            "[WARNING]".yellow().bold()
+        };
+        let source = if d.accounts.is_empty() {
+            "account data".to_string()
+        } else {
+            let mut names: Vec<_> = d.accounts.iter().map(|a| format!("`{}`", a)).collect();
+            names.sort();
+            names.join(", ")
+        };
+
+        println!(
+            "{} `{}` in `{}` deserializes {} without checking its {}; another account type could be substituted (type cosplay). \
+Prefer `Account<'info, T>` or verify it manually. ({}:{})\n",
+            level, d.name, fn_name, source, missing, file, d.line
+        );
+    }
+}
+
+/// Deserializations in a handler, and the accounts whose owner or
+/// discriminator it compares against something.
+struct Checks<'a> {
+    locals: &'a HashMap<String, Expr>,
+    found: Vec<Deserialization>,
+    owner_checked: HashSet<String>,
+    /// an owner comparison whose account could not be resolved
+    any_owner_checked: bool,
+    discriminator_checked: HashSet<String>,
+    any_discriminator_checked: bool,
+}
+
+impl Checks<'_> {
+    /// Records the owner and discriminator checks made by comparing `sides`,
+    /// or by testing them as a condition. Bare reads such as
+    /// `msg!("{}", acc.owner)` never get here.
+    fn compared(&mut self, sides: &[&Expr]) {
+        let mut marker = false;
+        let mut data = false;
+        for side in sides {
+            walk_expr(side, &mut |e: &Expr| match e {
+                Expr::Field(f) if matches!(&f.member, Member::Named(m) if m == "owner") => {
+                    self.any_owner_checked = true;
+                    if let Some(account) = account_info_ref(&f.base, self.locals) {
+                        self.owner_checked.insert(account);
+                    }
+                }
+                Expr::MethodCall(mc) if mc.method == "owner" => {
+                    self.any_owner_checked = true;
+                    if let Some(account) = account_info_ref(&mc.receiver, self.locals) {
+                        self.owner_checked.insert(account);
+                    }
+                }
+                Expr::Path(p) => {
+                    let last = p.path.segments.last().unwrap().ident.to_string();
+                    marker |= last.to_lowercase().contains("discriminator");
+                }
+                // `data[..8]`
+                Expr::Range(r)
+                    if r.start.is_none() && r.end.as_deref().and_then(int_lit) == Some(8) =>
+                {
+                    marker = true;
+                }
+                _ => {}
+            });
+            data |= reads_account_data(side, self.locals);
+        }
+        // the discriminator must be compared with the data it guards
+        if marker && data {
+            self.any_discriminator_checked = true;
+            for side in sides {
+                self.discriminator_checked
+                    .extend(accounts_in(side, self.locals));
+            }
+        }
+    }
+}
+
+impl ExprVisitor for Checks<'_> {
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Call(call) => {
+                if let Some(d) = deserialization(call, self.locals) {
+                    self.found.push(d);
+                }
+            }
+            Expr::Binary(b) if matches!(b.op, BinOp::Eq(_) | BinOp::Ne(_)) => {
+                self.compared(&[&b.left, &b.right]);
+            }
+            Expr::If(e) => self.compared(&[&e.cond]),
+            _ => {}
+        }
+    }
+
+    fn visit_macro(&mut self, mac: &Macro) {
+        let Some(name) = mac.path.get_ident().map(|i| i.to_string()) else {
+            return;
+        };
+        let compared = match name.as_str() {
+            "require" | "assert" => 1,
+            "require_eq" | "require_neq" | "require_keys_eq" | "require_keys_neq" | "assert_eq"
+            | "assert_ne" => 2,
+            _ => return,
+        };
+        if let Ok(args) = mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated) {
+            let sides: Vec<&Expr> = args.iter().take(compared).collect();
+            self.compared(&sides);
+        }
+    }
+}
+
+fn deserialization(call: &ExprCall, locals: &HashMap<String, Expr>) -> Option<Deserialization> {
+    let Expr::Path(ExprPath { path, .. }) = &*call.func else {
+        return None;
+    };
+    let segs: Vec<String> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+    let name = segs.last()?.as_str();
+
+    let (checks_discriminator, checks_owner) = match name {
+        // Borsh: no discriminator, no owner
+        "try_from_slice" | "deserialize" => (false, false),
+        // Anchor: `try_deserialize` checks the discriminator only
+        "try_deserialize" => (true, false),
+        "try_deserialize_unchecked" => (false, false),
+        // `Account::try_from_unchecked` checks the owner but not the discriminator
+        "try_from_unchecked" => (false, true),
+        // zero-copy casts
+        "from_bytes" | "from_bytes_mut" | "try_from_bytes" | "try_from_bytes_mut"
+        | "pod_read_unaligned" | "cast_ref" | "cast_mut"
+            if segs.len() == 1 || segs.iter().any(|s| s == "bytemuck") =>
+        {
+            (false, false)
+        }
+        _ => return None,
+    };
+
+    let on_account_data = call.args.iter().any(|a| reads_account_data(a, locals));
+    if !on_account_data && name != "try_from_unchecked" {
+        return None;
+    }
+
+    Some(Deserialization {
+        name: segs.join("::"),
+        accounts: call
+            .args
+            .iter()
+            .flat_map(|a| accounts_in(a, locals))
+            .collect(),
+        checks_discriminator,
+        checks_owner,
+        line: call.span().start().line,
+    })
+}
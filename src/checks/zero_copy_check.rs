@@ -0,0 +1,241 @@
+use crate::index::{AccountIndex, is_zero_copy};
+use crate::utils::account_type;
+use colored::*;
+use proc_macro2::TokenTree;
+use quote::ToTokens;
+use syn::{Attribute, Fields, ItemStruct, Type};
+
+/// Types that are never `Pod` (no fixed layout, invalid bit patterns or heap data).
+const NON_POD: &[&str] = &[
+    "bool", "char", "String", "Vec", "Option", "Box", "VecDeque", "HashMap", "BTreeMap", "HashSet",
+    "BTreeSet",
+];
+
+/// Check the layout of zero-copy account structs: an explicit `repr`, no
+/// non-`Pod` fields, no implicit padding and, for packed structs, no
+/// misaligned fields.
+pub fn check_zero_copy_struct(item_struct: &ItemStruct, file: &str, index: &AccountIndex) {
+    if !is_zero_copy(&item_struct.attrs) {
+        return;
+    }
+    let Fields::Named(fields) = &item_struct.fields else {
+        return;
+    };
+    let name = &item_struct.ident;
+    let line = name.span().start().line;
+
+    let repr = repr_hints(&item_struct.attrs);
+    // Anchor's `zero_copy` adds `repr(C)`, or `repr(packed)` for `zero_copy(unsafe)`
+    let anchor_macro = anchor_zero_copy(&item_struct.attrs);
+    let packed = repr.iter().any(|r| r == "packed") || anchor_macro == Some(true);
+    if anchor_macro.is_none()
+        && !repr
+            .iter()
+            .any(|r| r == "C" || r == "packed" || r == "transparent")
+    {
+        println!(
+            "{} Zero-copy struct `{}` has no `#[repr(C)]` or `#[repr(packed)]`, so its field order and padding are unspecified. ({}:{})\n",
+            "[ERROR]".red().bold(),
+            name,
+            file,
+            line
+        );
+    }
+
+    let mut has_non_pod = false;
+    for field in &fields.named {
+        if let Some(bad) = non_pod(&field.ty, index) {
+            has_non_pod = true;
+            let field_name = field.ident.as_ref().unwrap();
+            println!(
+                "{} `{}` in zero-copy struct `{}` has type `{}`, which is not `Pod`; zero-copy accounts can only hold plain integers, `Pubkey`s, arrays and other zero-copy structs. ({}:{})\n",
+                "[ERROR]".red().bold(),
+                field_name,
+                name,
+                bad,
+                file,
+                field_name.span().start().line
+            );
+        }
+    }
+    if has_non_pod {
+        return;
+    }
+
+    let mut offset = 0;
+    let mut struct_align = 1;
+    for field in &fields.named {
+        let Some((size, align)) = layout(&field.ty, index, 0) else {
+            return;
+        };
+        let field_name = field.ident.as_ref().unwrap();
+        let field_line = field_name.span().start().line;
+        if packed {
+            if offset % align != 0 {
+                println!(
+                    "{} `{}` in packed struct `{}` sits at offset {}, misaligned for its {}-byte alignment; references to it are undefined behavior. \
+Reorder fields or use `repr(C)`. ({}:{})\n",
+                    "[WARNING]".yellow().bold(),
+                    field_name,
+                    name,
+                    offset,
+                    align,
+                    file,
+                    field_line
+                );
+            }
+        } else {
+            let pad = (align - offset % align) % align;
+            if pad > 0 {
+                println!(
+                    "{} {} byte(s) of implicit padding before `{}` in zero-copy struct `{}`; `Pod` forbids padding. \
+Reorder fields or add an explicit `[u8; {}]` padding field. ({}:{})\n",
+                    "[WARNING]".yellow().bold(),
+                    pad,
+                    field_name,
+                    name,
+                    pad,
+                    file,
+                    field_line
+                );
+                offset += pad;
+            }
+            struct_align = struct_align.max(align);
+        }
+        offset += size;
+    }
+    let tail = (struct_align - offset % struct_align) % struct_align;
+    if tail > 0 {
+        println!(
+            "{} Zero-copy struct `{}` ends with {} byte(s) of implicit trailing padding; `Pod` forbids padding. \
+Add an explicit `[u8; {}]` padding field. ({}:{})\n",
+            "[WARNING]".yellow().bold(),
+            name,
+            tail,
+            tail,
+            file,
+            line
+        );
+    }
+}
+
+/// Flag `AccountLoader<'info, T>` fields whose `T` is not a zero-copy struct.
+pub fn check_account_loaders(item_struct: &ItemStruct, file: &str, index: &AccountIndex) {
+    let Fields::Named(fields) = &item_struct.fields else {
+        return;
+    };
+    for field in &fields.named {
+        let Some((wrapper, Some(inner))) = account_type(&field.ty) else {
+            continue;
+        };
+        if wrapper != "AccountLoader" {
+            continue;
+        }
+        let Some(data) = index.data_struct(&inner) else {
+            continue;
+        };
+        if is_zero_copy(&data.attrs) {
+            continue;
+        }
+        let field_name = field.ident.as_ref().unwrap();
+        println!(
+            "{} `{}` in struct `{}` is an `AccountLoader<{}>`, but `{}` is not declared `#[account(zero_copy)]`. ({}:{})\n",
+            "[ERROR]".red().bold(),
+            field_name,
+            item_struct.ident,
+            inner,
+            inner,
+            file,
+            field_name.span().start().line
+        );
+    }
+}
+
+/// Idents inside `#[repr(...)]`.
+fn repr_hints(attrs: &[Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|a| a.path().is_ident("repr"))
+        .flat_map(|a| {
+            a.meta
+                .require_list()
+                .map(|l| l.tokens.clone().into_iter().collect::<Vec<_>>())
+                .unwrap_or_default()
+        })
+        .filter_map(|t| match t {
+            TokenTree::Ident(i) => Some(i.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// `Some(packed)` when the struct uses Anchor's zero-copy macro.
+fn anchor_zero_copy(attrs: &[Attribute]) -> Option<bool> {
+    attrs.iter().find_map(|a| {
+        let tokens = a.to_token_stream().to_string();
+        let anchor = a.path().is_ident("zero_copy")
+            || (a.path().is_ident("account") && tokens.contains("zero_copy"));
+        anchor.then(|| tokens.contains("unsafe"))
+    })
+}
+
+/// The offending type name if `ty` cannot be part of a `Pod` struct.
+fn non_pod(ty: &Type, index: &AccountIndex) -> Option<String> {
+    match ty {
+        Type::Array(arr) => non_pod(&arr.elem, index),
+        Type::Path(tp) => {
+            let name = tp.path.segments.last()?.ident.to_string();
+            if NON_POD.contains(&name.as_str()) || index.is_enum(&name) {
+                return Some(name);
+            }
+            match index.data_struct(&name) {
+                Some(s) if !is_zero_copy(&s.attrs) => Some(name),
+                _ => None,
+            }
+        }
+        ty => Some(ty.to_token_stream().to_string()),
+    }
+}
+
+/// Size and alignment of a `Pod` field type as laid out on the SBF target.
+fn layout(ty: &Type, index: &AccountIndex, depth: usize) -> Option<(usize, usize)> {
+    if depth > 16 {
+        return None;
+    }
+    match ty {
+        Type::Array(arr) => {
+            let len = index.eval(&arr.len)? as usize;
+            let (size, align) = layout(&arr.elem, index, depth + 1)?;
+            Some((len * size, align))
+        }
+        Type::Path(tp) => {
+            let name = tp.path.segments.last()?.ident.to_string();
+            match name.as_str() {
+                "u8" | "i8" => Some((1, 1)),
+                "u16" | "i16" => Some((2, 2)),
+                "u32" | "i32" | "f32" => Some((4, 4)),
+                "u64" | "i64" | "f64" | "usize" | "isize" => Some((8, 8)),
+                // 16-byte integers are 8-aligned on SBF
+                "u128" | "i128" => Some((16, 8)),
+                "Pubkey" => Some((32, 1)),
+                name => {
+                    let s = index.data_struct(name)?;
+                    let Fields::Named(fields) = &s.fields else {
+                        return None;
+                    };
+                    let packed = repr_hints(&s.attrs).iter().any(|r| r == "packed")
+                        || anchor_zero_copy(&s.attrs) == Some(true);
+                    let (mut offset, mut align) = (0usize, 1);
+                    for f in &fields.named {
+                        let (size, a) = layout(&f.ty, index, depth + 1)?;
+                        let a = if packed { 1 } else { a };
+                        offset = offset.div_ceil(a) * a + size;
+                        align = align.max(a);
+                    }
+                    Some((offset.div_ceil(align) * align, align))
+                }
+            }
+        }
+        _ => None,
+    }
+}
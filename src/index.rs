@@ -0,0 +1,283 @@
+use crate::utils::int_lit;
+use proc_macro2::TokenTree;
+use quote::ToTokens;
+use std::collections::HashMap;
+use syn::{
+    Attribute, BinOp, Expr, Field, Fields, File, GenericArgument, ImplItem, Item, ItemEnum,
+    ItemStruct, PathArguments, Token, Type, punctuated::Punctuated,
+};
+
+/// Anchor's account discriminator.
+pub const DISCRIMINATOR_LEN: u128 = 8;
+
+/// Crate-wide view of the data types accounts are made of, built from every
+/// parsed file before any of them is analyzed.
+#[derive(Default)]
+pub struct AccountIndex {
+    /// `#[account]` structs by name
+    accounts: HashMap<String, ItemStruct>,
+    /// other structs and enums, for nested field types
+    structs: HashMap<String, ItemStruct>,
+    enums: HashMap<String, ItemEnum>,
+    /// `const MAX: usize = ..` and `impl T { const LEN: usize = ..; }` (as `T::LEN`)
+    consts: HashMap<String, Expr>,
+}
+
+impl AccountIndex {
+    pub fn from_files<'a>(files: impl IntoIterator<Item = &'a File>) -> Self {
+        let mut index = AccountIndex::default();
+        for file in files {
+            index.add_items(&file.items);
+        }
+        index
+    }
+
+    fn add_items(&mut self, items: &[Item]) {
+        for item in items {
+            match item {
+                Item::Struct(s) if is_account_attr(&s.attrs) => {
+                    self.accounts.insert(s.ident.to_string(), s.clone());
+                }
+                Item::Struct(s) => {
+                    self.structs.insert(s.ident.to_string(), s.clone());
+                }
+                Item::Enum(e) => {
+                    self.enums.insert(e.ident.to_string(), e.clone());
+                }
+                Item::Const(c) => {
+                    self.consts.insert(c.ident.to_string(), (*c.expr).clone());
+                }
+                Item::Impl(imp) if imp.trait_.is_none() => {
+                    let Type::Path(tp) = &*imp.self_ty else {
+                        continue;
+                    };
+                    let Some(owner) = tp.path.segments.last() else {
+                        continue;
+                    };
+                    for inner in &imp.items {
+                        if let ImplItem::Const(c) = inner {
+                            self.consts
+                                .insert(format!("{}::{}", owner.ident, c.ident), c.expr.clone());
+                        }
+                    }
+                }
+                Item::Mod(m) => {
+                    if let Some((_, items)) = &m.content {
+                        self.add_items(items);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The `#[account]` struct named `name`.
+    pub fn account(&self, name: &str) -> Option<&ItemStruct> {
+        self.accounts.get(name)
+    }
+
+    /// Any struct named `name`, `#[account]` or not.
+    pub fn data_struct(&self, name: &str) -> Option<&ItemStruct> {
+        self.accounts.get(name).or_else(|| self.structs.get(name))
+    }
+
+    pub fn is_enum(&self, name: &str) -> bool {
+        self.enums.contains_key(name)
+    }
+
+    /// Borsh-serialized size of a struct or enum, without the discriminator.
+    /// `None` when some field has no statically known bound.
+    pub fn type_size(&self, name: &str) -> Option<u128> {
+        self.named_size(name, 0)
+    }
+
+    fn named_size(&self, name: &str, depth: usize) -> Option<u128> {
+        if depth > 16 {
+            return None;
+        }
+        if let Some(s) = self.accounts.get(name).or_else(|| self.structs.get(name)) {
+            return self.fields_size(&s.fields, depth + 1);
+        }
+        let e = self.enums.get(name)?;
+        let mut largest = 0;
+        for variant in &e.variants {
+            largest = largest.max(self.fields_size(&variant.fields, depth + 1)?);
+        }
+        Some(1 + largest)
+    }
+
+    fn fields_size(&self, fields: &Fields, depth: usize) -> Option<u128> {
+        let fields: Vec<&Field> = match fields {
+            Fields::Named(f) => f.named.iter().collect(),
+            Fields::Unnamed(f) => f.unnamed.iter().collect(),
+            Fields::Unit => Vec::new(),
+        };
+        let mut total = 0;
+        for field in fields {
+            let mut max_len = max_len(&field.attrs).into_iter();
+            total += self.ty_size(&field.ty, &mut max_len, depth)?;
+        }
+        Some(total)
+    }
+
+    /// `max_len` supplies the `#[max_len(..)]` bounds of nested collections,
+    /// outermost first.
+    fn ty_size(
+        &self,
+        ty: &Type,
+        max_len: &mut impl Iterator<Item = Expr>,
+        depth: usize,
+    ) -> Option<u128> {
+        match ty {
+            Type::Array(arr) => {
+                let len = self.eval(&arr.len)?;
+                Some(len * self.ty_size(&arr.elem, max_len, depth)?)
+            }
+            Type::Tuple(t) => t
+                .elems
+                .iter()
+                .map(|e| self.ty_size(e, max_len, depth))
+                .sum(),
+            Type::Path(tp) => {
+                let seg = tp.path.segments.last()?;
+                let generic = match &seg.arguments {
+                    PathArguments::AngleBracketed(ab) => ab.args.iter().find_map(|a| match a {
+                        GenericArgument::Type(t) => Some(t),
+                        _ => None,
+                    }),
+                    _ => None,
+                };
+                match seg.ident.to_string().as_str() {
+                    "bool" | "u8" | "i8" => Some(1),
+                    "u16" | "i16" => Some(2),
+                    "u32" | "i32" | "f32" => Some(4),
+                    "u64" | "i64" | "f64" => Some(8),
+                    "u128" | "i128" => Some(16),
+                    "Pubkey" => Some(32),
+                    "String" => Some(4 + self.eval(&max_len.next()?)?),
+                    "Vec" => {
+                        let len = self.eval(&max_len.next()?)?;
+                        Some(4 + len * self.ty_size(generic?, max_len, depth)?)
+                    }
+                    "Option" => Some(1 + self.ty_size(generic?, max_len, depth)?),
+                    "Box" => self.ty_size(generic?, max_len, depth),
+                    name => self.named_size(name, depth + 1),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Constant value of a size expression: literals, `+ - *`, known consts,
+    /// `T::LEN` associated consts, `T::INIT_SPACE` and `size_of::<T>()` of primitives.
+    pub fn eval(&self, expr: &Expr) -> Option<u128> {
+        self.eval_depth(expr, 0)
+    }
+
+    fn eval_depth(&self, expr: &Expr, depth: usize) -> Option<u128> {
+        if depth > 16 {
+            return None;
+        }
+        let eval = |e: &Expr| self.eval_depth(e, depth + 1);
+        match expr {
+            Expr::Lit(_) => int_lit(expr),
+            Expr::Paren(p) => eval(&p.expr),
+            Expr::Group(g) => eval(&g.expr),
+            Expr::Cast(c) => eval(&c.expr),
+            Expr::Binary(b) => {
+                let (l, r) = (eval(&b.left)?, eval(&b.right)?);
+                match b.op {
+                    BinOp::Add(_) => l.checked_add(r),
+                    BinOp::Sub(_) => l.checked_sub(r),
+                    BinOp::Mul(_) => l.checked_mul(r),
+                    BinOp::Div(_) => l.checked_div(r),
+                    _ => None,
+                }
+            }
+            Expr::Path(p) => {
+                let segs: Vec<String> = p
+                    .path
+                    .segments
+                    .iter()
+                    .map(|s| s.ident.to_string())
+                    .collect();
+                match segs.as_slice() {
+                    [.., owner, last] if last == "INIT_SPACE" => self.type_size(owner),
+                    [.., owner, last] => self
+                        .consts
+                        .get(&format!("{}::{}", owner, last))
+                        .or_else(|| self.consts.get(last))
+                        .and_then(eval),
+                    [name] => self.consts.get(name).and_then(eval),
+                    [] => None,
+                }
+            }
+            // `std::mem::size_of::<u64>()`
+            Expr::Call(c) if c.args.is_empty() => {
+                let Expr::Path(p) = &*c.func else {
+                    return None;
+                };
+                let seg = p.path.segments.last()?;
+                if seg.ident != "size_of" {
+                    return None;
+                }
+                let PathArguments::AngleBracketed(ab) = &seg.arguments else {
+                    return None;
+                };
+                match ab.args.first()? {
+                    GenericArgument::Type(ty) => match ty {
+                        Type::Path(tp) if tp.path.get_ident().is_some() => {
+                            let name = tp.path.get_ident()?.to_string();
+                            // only primitives have the same in-memory and Borsh size
+                            if self.structs.contains_key(&name) || self.enums.contains_key(&name) {
+                                None
+                            } else {
+                                self.ty_size(ty, &mut std::iter::empty(), depth)
+                            }
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// `#[account]`, `#[account(zero_copy)]`, ... on a struct.
+pub fn is_account_attr(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|a| a.path().is_ident("account"))
+}
+
+/// `#[account(zero_copy)]`, `#[zero_copy]` or a manual `#[derive(Pod)]`.
+pub fn is_zero_copy(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|a| {
+        a.path().is_ident("zero_copy")
+            || (a.path().is_ident("account") || a.path().is_ident("derive"))
+                && a.to_token_stream()
+                    .into_iter()
+                    .any(|t| has_ident(t, &["zero_copy", "Pod"]))
+    })
+}
+
+fn has_ident(tree: TokenTree, names: &[&str]) -> bool {
+    match tree {
+        TokenTree::Ident(i) => names.iter().any(|n| i == n),
+        TokenTree::Group(g) => g.stream().into_iter().any(|t| has_ident(t, names)),
+        _ => false,
+    }
+}
+
+/// Bounds of a field's `#[max_len(..)]` attribute, outermost first.
+fn max_len(attrs: &[Attribute]) -> Vec<Expr> {
+    attrs
+        .iter()
+        .filter(|a| a.path().is_ident("max_len"))
+        .filter_map(|a| {
+            a.parse_args_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+                .ok()
+        })
+        .flatten()
+        .collect()
+}
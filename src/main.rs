@@ -1,7 +1,10 @@
 mod analyzer;
 mod utils;
 mod checks;
+mod index;
+mod visit;
 
+use checks::cpi_check::CpiCatalog;
 use clap::Parser;
 
 /// SageGuard-RS: A static analyzer for Anchor smart contracts
@@ -10,11 +13,22 @@ use clap::Parser;
 struct Args {
     /// Path to analyze
     path: String,
+
+    /// Extra CPI function to recognize, as `<module>::<function>` (repeatable)
+    #[arg(long = "cpi", value_name = "MODULE::FUNCTION")]
+    cpi: Vec<String>,
 }
 
 fn main() {
     let args = Args::parse();
-    if let Err(e) = analyzer::analyze_path(&args.path) {
+    let mut catalog = CpiCatalog::default();
+    for entry in &args.cpi {
+        if let Err(e) = catalog.add(entry) {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    }
+    if let Err(e) = analyzer::analyze_path(&args.path, &catalog) {
         eprintln!("{}", e);
     }
 }
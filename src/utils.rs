@@ -1,4 +1,13 @@
-use syn::{Attribute, Error};
+use crate::visit::{ExprVisitor, walk_expr, walk_fn};
+use std::collections::{HashMap, HashSet};
+use syn::{
+    Attribute, BinOp, Error, Expr, ExprField, ExprLit, ExprPath, Field, Fields, File, FnArg,
+    GenericArgument, Ident, Item, ItemFn, ItemStruct, Lit, Local, Member, Pat, Path, PathArguments,
+    Token, Type, UseTree, parse::ParseStream,
+};
+
+/// `#[derive(Accounts)]` structs of a file, keyed by struct name.
+pub type AccountsStructMap<'a> = HashMap<String, &'a ItemStruct>;
 
 /// Detects if struct is #[derive(Accounts)]
 
@@ -21,3 +30,560 @@ pub fn is_anchor_account_struct(attrs: &[Attribute]) -> bool {
         }
     })
 }
+
+/// Name of the Accounts struct a handler takes, i.e. `Foo` for `ctx: Context<Foo>`.
+pub fn context_struct_name(func: &ItemFn) -> Option<String> {
+    func.sig.inputs.iter().find_map(|arg| {
+        let FnArg::Typed(pat_ty) = arg else {
+            return None;
+        };
+        let Type::Path(tp) = &*pat_ty.ty else {
+            return None;
+        };
+        let seg = tp.path.segments.last()?;
+        if seg.ident != "Context" {
+            return None;
+        }
+        let PathArguments::AngleBracketed(ab) = &seg.arguments else {
+            return None;
+        };
+        ab.args.iter().find_map(|arg| match arg {
+            GenericArgument::Type(Type::Path(inner)) => {
+                Some(inner.path.segments.last()?.ident.to_string())
+            }
+            _ => None,
+        })
+    })
+}
+
+/// If `expr` is `ctx.accounts.foo`, return `"foo"`.
+pub fn ctx_account_name(expr: &Expr) -> Option<String> {
+    let Expr::Field(ExprField { base, member, .. }) = expr else {
+        return None;
+    };
+    let Expr::Field(inner) = &**base else {
+        return None;
+    };
+    let Expr::Path(ExprPath { path, .. }) = &*inner.base else {
+        return None;
+    };
+    match (&inner.member, member) {
+        (Member::Named(accounts), Member::Named(field))
+            if accounts == "accounts" && path.is_ident("ctx") =>
+        {
+            Some(field.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Splits an account field type into its wrapper and inner type names, looking
+/// through `Box`: `Box<Account<'info, Vault>>` gives `("Account", Some("Vault"))`.
+pub fn account_type(ty: &Type) -> Option<(String, Option<String>)> {
+    let Type::Path(tp) = ty else {
+        return None;
+    };
+    let seg = tp.path.segments.last()?;
+    let inner = match &seg.arguments {
+        PathArguments::AngleBracketed(ab) => ab.args.iter().rev().find_map(|arg| match arg {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        }),
+        _ => None,
+    };
+
+    if seg.ident == "Box" {
+        return account_type(inner?);
+    }
+
+    let inner_name = inner.and_then(|ty| match ty {
+        Type::Path(p) => p.path.segments.last().map(|s| s.ident.to_string()),
+        _ => None,
+    });
+    Some((seg.ident.to_string(), inner_name))
+}
+
+/// Every function of a file, including those nested in inline modules (the
+/// `#[program]` module, `instructions` modules, ...).
+pub fn file_fns(file: &File) -> Vec<&ItemFn> {
+    fn collect<'a>(items: &'a [Item], out: &mut Vec<&'a ItemFn>) {
+        for item in items {
+            match item {
+                Item::Fn(func) => out.push(func),
+                Item::Mod(m) => {
+                    if let Some((_, items)) = &m.content {
+                        collect(items, out);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut fns = Vec::new();
+    collect(&file.items, &mut fns);
+    fns
+}
+
+/// `use` declarations of a file, so paths can be resolved to what they import.
+#[derive(Default)]
+pub struct Imports {
+    /// imported name (or `as` alias) → full path
+    names: HashMap<String, Vec<String>>,
+    /// prefixes of `use foo::bar::*`
+    globs: Vec<Vec<String>>,
+}
+
+impl Imports {
+    /// Collects every `use` in the file, including those inside inline modules.
+    pub fn from_file(file: &File) -> Self {
+        let mut imports = Imports::default();
+        imports.collect_items(&file.items);
+        imports
+    }
+
+    fn collect_items(&mut self, items: &[Item]) {
+        for item in items {
+            match item {
+                Item::Use(u) => self.collect_tree(&u.tree, &mut Vec::new()),
+                Item::Mod(m) => {
+                    if let Some((_, inner)) = &m.content {
+                        self.collect_items(inner);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn collect_tree(&mut self, tree: &UseTree, prefix: &mut Vec<String>) {
+        match tree {
+            UseTree::Path(p) => {
+                prefix.push(p.ident.to_string());
+                self.collect_tree(&p.tree, prefix);
+                prefix.pop();
+            }
+            UseTree::Name(n) => {
+                // `use anchor_spl::token::{self}` imports `token`
+                if n.ident == "self" {
+                    if let Some(last) = prefix.last() {
+                        self.names.insert(last.clone(), prefix.clone());
+                    }
+                } else {
+                    let mut full = prefix.clone();
+                    full.push(n.ident.to_string());
+                    self.names.insert(n.ident.to_string(), full);
+                }
+            }
+            UseTree::Rename(r) => {
+                let mut full = prefix.clone();
+                if r.ident != "self" {
+                    full.push(r.ident.to_string());
+                }
+                self.names.insert(r.rename.to_string(), full);
+            }
+            UseTree::Glob(_) => self.globs.push(prefix.clone()),
+            UseTree::Group(g) => {
+                for t in &g.items {
+                    self.collect_tree(t, prefix);
+                }
+            }
+        }
+    }
+
+    /// Expands the first segment of `path` through the imports, e.g.
+    /// `token::transfer` with `use anchor_spl::token;` gives
+    /// `anchor_spl::token::transfer`. Unknown paths are returned as written.
+    pub fn resolve(&self, path: &Path) -> Vec<String> {
+        let segments: Vec<String> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+        match segments.first().and_then(|first| self.names.get(first)) {
+            Some(full) => full.iter().chain(&segments[1..]).cloned().collect(),
+            None => segments,
+        }
+    }
+
+    /// Prefixes of glob imports (`use anchor_spl::token::*` gives `anchor_spl::token`).
+    pub fn globs(&self) -> &[Vec<String>] {
+        &self.globs
+    }
+}
+
+/// One entry of an `#[account(...)]` attribute, e.g. `seeds = [...]`,
+/// `token::mint = mint` or a bare `mut`.
+pub struct AccountConstraint {
+    pub key: String,
+    pub value: Option<Expr>,
+}
+
+/// Parses every `#[account(...)]` attribute on a field. Entries after one that
+/// fails to parse are dropped.
+pub fn account_constraints(field: &Field) -> Vec<AccountConstraint> {
+    let mut out = Vec::new();
+    for attr in &field.attrs {
+        if !attr.path().is_ident("account") {
+            continue;
+        }
+        let _ = attr.parse_nested_meta(|meta| {
+            let key = meta
+                .path
+                .segments
+                .iter()
+                .map(|s| s.ident.to_string())
+                .collect::<Vec<_>>()
+                .join("::");
+            let value = if meta.input.peek(Token![=]) {
+                Some(meta.value()?.parse::<Expr>()?)
+            } else {
+                None
+            };
+            // custom error: `has_one = authority @ MyError::Unauthorized`
+            if meta.input.peek(Token![@]) {
+                meta.input.parse::<Token![@]>()?;
+                meta.input.parse::<Expr>()?;
+            }
+            out.push(AccountConstraint { key, value });
+            Ok(())
+        });
+    }
+    out
+}
+
+/// Looks up the first constraint named `key`.
+pub fn find_constraint<'a>(
+    cs: &'a [AccountConstraint],
+    key: &str,
+) -> Option<&'a AccountConstraint> {
+    cs.iter().find(|c| c.key == key)
+}
+
+/// The elements of a `seeds = [...]` constraint, if present.
+pub fn constraint_seeds(cs: &[AccountConstraint]) -> Option<Vec<Expr>> {
+    match &find_constraint(cs, "seeds")?.value {
+        Some(Expr::Array(arr)) => Some(arr.elems.iter().cloned().collect()),
+        _ => None,
+    }
+}
+
+/// Every `let name = <expr>` in a handler (later bindings shadow earlier ones).
+pub fn collect_locals(func: &ItemFn) -> HashMap<String, Expr> {
+    struct Locals(HashMap<String, Expr>);
+    impl ExprVisitor for Locals {
+        fn visit_local(&mut self, local: &Local) {
+            let pat = match &local.pat {
+                Pat::Type(pt) => &*pt.pat,
+                pat => pat,
+            };
+            if let (Pat::Ident(pi), Some(init)) = (pat, &local.init) {
+                self.0.insert(pi.ident.to_string(), (*init.expr).clone());
+            }
+        }
+    }
+
+    let mut locals = Locals(HashMap::new());
+    walk_fn(func, &mut locals);
+    locals.0
+}
+
+/// Every `ctx.accounts.*` field referenced by `expr`, following local bindings.
+pub fn accounts_in(expr: &Expr, locals: &HashMap<String, Expr>) -> HashSet<String> {
+    let mut found = HashSet::new();
+    let mut seen = HashSet::new();
+    let mut pending = vec![expr];
+    while let Some(next) = pending.pop() {
+        walk_expr(next, &mut |e: &Expr| {
+            if let Some(name) = ctx_account_name(e) {
+                found.insert(name);
+            } else if let Expr::Path(p) = e
+                && let Some(ident) = p.path.get_ident()
+                && seen.insert(ident.to_string())
+                && let Some(init) = locals.get(&ident.to_string())
+            {
+                pending.push(init);
+            }
+        });
+    }
+    found
+}
+
+/// Resolves `ctx.accounts.foo`, or a local bound to `&mut ctx.accounts.foo`, to `"foo"`.
+pub fn account_ref(expr: &Expr, locals: &HashMap<String, Expr>) -> Option<String> {
+    match strip_ref(expr) {
+        Expr::Path(p) => {
+            let init = locals.get(&p.path.get_ident()?.to_string())?;
+            ctx_account_name(strip_ref(init))
+        }
+        expr => ctx_account_name(expr),
+    }
+}
+
+/// Strips `&`, `&mut` and parentheses.
+pub fn strip_ref(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Reference(r) => strip_ref(&r.expr),
+        Expr::Paren(p) => strip_ref(&p.expr),
+        _ => expr,
+    }
+}
+
+/// Names of a handler's instruction arguments, i.e. every parameter but the `Context`.
+pub fn instruction_args(func: &ItemFn) -> HashSet<String> {
+    func.sig
+        .inputs
+        .iter()
+        .filter_map(|arg| match arg {
+            FnArg::Typed(pt) => match (&*pt.pat, &*pt.ty) {
+                (_, Type::Path(tp))
+                    if tp
+                        .path
+                        .segments
+                        .last()
+                        .is_some_and(|s| s.ident == "Context") =>
+                {
+                    None
+                }
+                (Pat::Ident(pi), _) => Some(pi.ident.to_string()),
+                _ => None,
+            },
+            FnArg::Receiver(_) => None,
+        })
+        .collect()
+}
+
+/// Names declared by `#[instruction(amount: u64, bump: u8)]` on an Accounts struct.
+pub fn instruction_attr_args(item_struct: &ItemStruct) -> HashSet<String> {
+    instruction_attr_types(item_struct).into_keys().collect()
+}
+
+/// Declared types of the `#[instruction(...)]` arguments, keyed by name.
+pub fn instruction_attr_types(item_struct: &ItemStruct) -> HashMap<String, Type> {
+    let mut types = HashMap::new();
+    for attr in &item_struct.attrs {
+        if !attr.path().is_ident("instruction") {
+            continue;
+        }
+        let _ = attr.parse_args_with(|input: ParseStream| {
+            while !input.is_empty() {
+                let name: Ident = input.parse()?;
+                input.parse::<Token![:]>()?;
+                let ty: Type = input.parse()?;
+                types.insert(name.to_string(), ty);
+                if input.is_empty() {
+                    break;
+                }
+                input.parse::<Token![,]>()?;
+            }
+            Ok(())
+        });
+    }
+    types
+}
+
+/// First identifier from `names` that `expr` mentions as a plain path.
+pub fn mentioned_ident(expr: &Expr, names: &HashSet<String>) -> Option<String> {
+    let mut found = None;
+    walk_expr(expr, &mut |e: &Expr| {
+        if found.is_none()
+            && let Expr::Path(p) = e
+            && let Some(ident) = p.path.get_ident()
+            && names.contains(&ident.to_string())
+        {
+            found = Some(ident.to_string());
+        }
+    });
+    found
+}
+
+/// Whether `expr` (following locals) reads raw account bytes, e.g.
+/// `acc.try_borrow_data()?[8]` or `acc.data.borrow()[0]`.
+pub fn reads_account_data(expr: &Expr, locals: &HashMap<String, Expr>) -> bool {
+    let mut found = false;
+    let mut pending = vec![strip_ref(expr)];
+    let mut depth = 0;
+    while let Some(next) = pending.pop() {
+        depth += 1;
+        if depth > 16 {
+            break;
+        }
+        walk_expr(next, &mut |e: &Expr| match e {
+            Expr::MethodCall(mc) if mc.method == "try_borrow_data" || mc.method == "data" => {
+                found = true
+            }
+            Expr::Field(f) if matches!(&f.member, Member::Named(m) if m == "data") => found = true,
+            Expr::Path(p) => {
+                if let Some(init) = p.path.get_ident().and_then(|i| locals.get(&i.to_string())) {
+                    pending.push(init);
+                }
+            }
+            _ => {}
+        });
+    }
+    found
+}
+
+/// Value of an integer literal such as `8` or `32u64`.
+pub fn int_lit(expr: &Expr) -> Option<u128> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(i), ..
+        }) => i.base10_parse().ok(),
+        Expr::Paren(p) => int_lit(&p.expr),
+        Expr::Group(g) => int_lit(&g.expr),
+        _ => None,
+    }
+}
+
+/// Like [`account_ref`], but also sees through `.to_account_info()`, `.as_ref()`,
+/// `.clone()` and `?`, as used when handling raw `AccountInfo`s.
+pub fn account_info_ref(expr: &Expr, locals: &HashMap<String, Expr>) -> Option<String> {
+    fn resolve(expr: &Expr, locals: &HashMap<String, Expr>, depth: usize) -> Option<String> {
+        if depth > 8 {
+            return None;
+        }
+        match strip_ref(expr) {
+            Expr::MethodCall(mc)
+                if mc.args.is_empty()
+                    && matches!(
+                        mc.method.to_string().as_str(),
+                        "to_account_info" | "as_ref" | "clone"
+                    ) =>
+            {
+                resolve(&mc.receiver, locals, depth + 1)
+            }
+            Expr::Try(t) => resolve(&t.expr, locals, depth + 1),
+            Expr::Path(p) => {
+                let init = locals.get(&p.path.get_ident()?.to_string())?;
+                resolve(init, locals, depth + 1)
+            }
+            expr => ctx_account_name(expr),
+        }
+    }
+    resolve(expr, locals, 0)
+}
+
+/// The account whose lamports `expr` dereferences, for the place expressions
+/// `**acc.try_borrow_mut_lamports()?` and `**acc.lamports.borrow_mut()`.
+pub fn lamports_ref(expr: &Expr, locals: &HashMap<String, Expr>) -> Option<String> {
+    match strip_ref(expr) {
+        Expr::Unary(u) => lamports_ref(&u.expr, locals),
+        Expr::Try(t) => lamports_ref(&t.expr, locals),
+        Expr::MethodCall(mc) if mc.method == "try_borrow_mut_lamports" => {
+            account_info_ref(&mc.receiver, locals)
+        }
+        Expr::MethodCall(mc) if mc.method == "borrow_mut" => match strip_ref(&mc.receiver) {
+            Expr::Field(f) if matches!(&f.member, Member::Named(m) if m == "lamports") => {
+                account_info_ref(&f.base, locals)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// How a statement changes an account's lamports.
+pub enum LamportChange<'a> {
+    /// `= 0` or `-= acc.lamports()`: the whole balance leaves, as in a close
+    Drain,
+    /// `-= amount`, `= acc.lamports().checked_sub(amount)..`
+    Debit(&'a Expr),
+    /// `+= amount`, `= acc.lamports().checked_add(amount)..`
+    Credit(&'a Expr),
+    /// any other assignment to the balance
+    Set,
+}
+
+/// A direct write to the lamports of `account`, shared by the checks that
+/// track closes, transfers and writability so they classify writes alike.
+pub struct LamportWrite<'a> {
+    pub account: String,
+    pub change: LamportChange<'a>,
+}
+
+/// Classifies `expr` if it assigns to `**acc.try_borrow_mut_lamports()?` or
+/// `**acc.lamports.borrow_mut()`.
+pub fn lamport_write<'a>(
+    expr: &'a Expr,
+    locals: &HashMap<String, Expr>,
+) -> Option<LamportWrite<'a>> {
+    let (account, change) = match expr {
+        Expr::Assign(a) => {
+            let account = lamports_ref(&a.left, locals)?;
+            let change = if int_lit(&a.right) == Some(0) {
+                LamportChange::Drain
+            } else {
+                match checked_op(&a.right) {
+                    Some(("checked_sub" | "saturating_sub", amount)) => {
+                        LamportChange::Debit(amount)
+                    }
+                    Some((_, amount)) => LamportChange::Credit(amount),
+                    None => LamportChange::Set,
+                }
+            };
+            (account, change)
+        }
+        Expr::Binary(b) => {
+            let account = lamports_ref(&b.left, locals)?;
+            let change = match b.op {
+                BinOp::SubAssign(_) if mentions_method(&b.right, "lamports") => {
+                    LamportChange::Drain
+                }
+                BinOp::SubAssign(_) => LamportChange::Debit(&b.right),
+                BinOp::AddAssign(_) => LamportChange::Credit(&b.right),
+                BinOp::MulAssign(_) | BinOp::DivAssign(_) | BinOp::RemAssign(_) => {
+                    LamportChange::Set
+                }
+                _ => return None,
+            };
+            (account, change)
+        }
+        _ => return None,
+    };
+    Some(LamportWrite { account, change })
+}
+
+/// The method and argument of `x.checked_sub(amount).unwrap()`,
+/// `x.saturating_add(amount)` or `...ok_or(..)?`.
+fn checked_op(expr: &Expr) -> Option<(&'static str, &Expr)> {
+    match expr {
+        Expr::MethodCall(mc) => {
+            let method = [
+                "checked_sub",
+                "checked_add",
+                "saturating_sub",
+                "saturating_add",
+            ]
+            .into_iter()
+            .find(|m| mc.method == m);
+            match method {
+                Some(m) => Some((m, mc.args.first()?)),
+                None => checked_op(&mc.receiver),
+            }
+        }
+        Expr::Try(t) => checked_op(&t.expr),
+        Expr::Paren(p) => checked_op(&p.expr),
+        _ => None,
+    }
+}
+
+/// Whether `expr` calls a method named `name` anywhere inside it.
+pub fn mentions_method(expr: &Expr, name: &str) -> bool {
+    let mut found = false;
+    walk_expr(expr, &mut |e: &Expr| {
+        if let Expr::MethodCall(mc) = e
+            && mc.method == name
+        {
+            found = true;
+        }
+    });
+    found
+}
+
+/// Looks up a named field of an Accounts struct.
+pub fn find_field<'a>(item_struct: &'a ItemStruct, name: &str) -> Option<&'a Field> {
+    match &item_struct.fields {
+        Fields::Named(fields) => fields
+            .named
+            .iter()
+            .find(|f| f.ident.as_ref().is_some_and(|i| i == name)),
+        _ => None,
+    }
+}
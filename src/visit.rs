@@ -0,0 +1,158 @@
+use syn::{Block, Expr, ItemFn, Local, Macro, Stmt, Token, punctuated::Punctuated};
+
+/// Callbacks for walking a handler body.
+///
+/// Unlike the per-check walkers, this one also descends into the arguments of
+/// function-like macros (`require!`, `msg!`, ...) when they parse as a
+/// comma-separated list of expressions.
+pub trait ExprVisitor {
+    fn visit_expr(&mut self, _expr: &Expr) {}
+    fn visit_local(&mut self, _local: &Local) {}
+    fn visit_macro(&mut self, _mac: &Macro) {}
+}
+
+impl<F: FnMut(&Expr)> ExprVisitor for F {
+    fn visit_expr(&mut self, expr: &Expr) {
+        self(expr)
+    }
+}
+
+pub fn walk_fn<V: ExprVisitor + ?Sized>(func: &ItemFn, v: &mut V) {
+    walk_block(&func.block, v);
+}
+
+pub fn walk_block<V: ExprVisitor + ?Sized>(block: &Block, v: &mut V) {
+    for stmt in &block.stmts {
+        walk_stmt(stmt, v);
+    }
+}
+
+pub fn walk_stmt<V: ExprVisitor + ?Sized>(stmt: &Stmt, v: &mut V) {
+    match stmt {
+        Stmt::Expr(expr, _) => walk_expr(expr, v),
+
+        Stmt::Local(local) => {
+            v.visit_local(local);
+            if let Some(init) = &local.init {
+                walk_expr(&init.expr, v);
+                if let Some((_, diverge)) = &init.diverge {
+                    walk_expr(diverge, v);
+                }
+            }
+        }
+
+        Stmt::Macro(m) => walk_macro(&m.mac, v),
+
+        Stmt::Item(_) => {}
+    }
+}
+
+/// Visits `expr` and then every sub-expression, in source order.
+pub fn walk_expr<V: ExprVisitor + ?Sized>(expr: &Expr, v: &mut V) {
+    v.visit_expr(expr);
+
+    match expr {
+        Expr::Array(e) => e.elems.iter().for_each(|x| walk_expr(x, v)),
+        Expr::Assign(e) => {
+            walk_expr(&e.left, v);
+            walk_expr(&e.right, v);
+        }
+        Expr::Async(e) => walk_block(&e.block, v),
+        Expr::Await(e) => walk_expr(&e.base, v),
+        Expr::Binary(e) => {
+            walk_expr(&e.left, v);
+            walk_expr(&e.right, v);
+        }
+        Expr::Block(e) => walk_block(&e.block, v),
+        Expr::Break(e) => {
+            if let Some(inner) = &e.expr {
+                walk_expr(inner, v);
+            }
+        }
+        Expr::Call(e) => {
+            walk_expr(&e.func, v);
+            e.args.iter().for_each(|x| walk_expr(x, v));
+        }
+        Expr::Cast(e) => walk_expr(&e.expr, v),
+        Expr::Closure(e) => walk_expr(&e.body, v),
+        Expr::Field(e) => walk_expr(&e.base, v),
+        Expr::ForLoop(e) => {
+            walk_expr(&e.expr, v);
+            walk_block(&e.body, v);
+        }
+        Expr::Group(e) => walk_expr(&e.expr, v),
+        Expr::If(e) => {
+            walk_expr(&e.cond, v);
+            walk_block(&e.then_branch, v);
+            if let Some((_, else_expr)) = &e.else_branch {
+                walk_expr(else_expr, v);
+            }
+        }
+        Expr::Index(e) => {
+            walk_expr(&e.expr, v);
+            walk_expr(&e.index, v);
+        }
+        Expr::Let(e) => walk_expr(&e.expr, v),
+        Expr::Loop(e) => walk_block(&e.body, v),
+        Expr::Macro(e) => walk_macro(&e.mac, v),
+        Expr::Match(e) => {
+            walk_expr(&e.expr, v);
+            for arm in &e.arms {
+                if let Some((_, guard)) = &arm.guard {
+                    walk_expr(guard, v);
+                }
+                walk_expr(&arm.body, v);
+            }
+        }
+        Expr::MethodCall(e) => {
+            walk_expr(&e.receiver, v);
+            e.args.iter().for_each(|x| walk_expr(x, v));
+        }
+        Expr::Paren(e) => walk_expr(&e.expr, v),
+        Expr::Range(e) => {
+            if let Some(start) = &e.start {
+                walk_expr(start, v);
+            }
+            if let Some(end) = &e.end {
+                walk_expr(end, v);
+            }
+        }
+        Expr::Reference(e) => walk_expr(&e.expr, v),
+        Expr::Repeat(e) => {
+            walk_expr(&e.expr, v);
+            walk_expr(&e.len, v);
+        }
+        Expr::Return(e) => {
+            if let Some(inner) = &e.expr {
+                walk_expr(inner, v);
+            }
+        }
+        Expr::Struct(e) => {
+            e.fields.iter().for_each(|f| walk_expr(&f.expr, v));
+            if let Some(rest) = &e.rest {
+                walk_expr(rest, v);
+            }
+        }
+        Expr::Try(e) => walk_expr(&e.expr, v),
+        Expr::TryBlock(e) => walk_block(&e.block, v),
+        Expr::Tuple(e) => e.elems.iter().for_each(|x| walk_expr(x, v)),
+        Expr::Unary(e) => walk_expr(&e.expr, v),
+        Expr::Unsafe(e) => walk_block(&e.block, v),
+        Expr::While(e) => {
+            walk_expr(&e.cond, v);
+            walk_block(&e.body, v);
+        }
+
+        // literals, paths and anything else without sub-expressions
+        _ => {}
+    }
+}
+
+fn walk_macro<V: ExprVisitor + ?Sized>(mac: &Macro, v: &mut V) {
+    v.visit_macro(mac);
+    if let Ok(args) = mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated) {
+        for arg in &args {
+            walk_expr(arg, v);
+        }
+    }
+}